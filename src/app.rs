@@ -1,22 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use git2::{Repository, RepositoryOpenFlags};
+
 use crate::{
+    changelog,
+    cli::Output,
     comment_generator,
-    committer::Committer,
+    committer::{CommitOptions, Committer},
     config::{self, ModelProvider},
     confirmor::Confirmor,
+    history::History,
+    hooks::Hooks,
     prompt_translator::{AnthropicTranslator, OpenAITranslator, PromptTranslator},
     UserError,
 };
 
-pub struct App {}
+pub struct App {
+    /// `-C`: where to look for the repository instead of the current directory.
+    directory: Option<PathBuf>,
+    git_dir: Option<PathBuf>,
+    work_tree: Option<PathBuf>,
+}
 
 impl App {
-    pub fn new() -> anyhow::Result<Self> {
-        Ok(App {})
+    pub fn new(
+        directory: Option<PathBuf>,
+        git_dir: Option<PathBuf>,
+        work_tree: Option<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        // git exports these for `--git-dir` and `--work-tree`, so hooks and editors find the same repository
+        let base = std::env::current_dir()?.join(directory.as_deref().unwrap_or(Path::new(".")));
+        if let Some(git_dir) = &git_dir {
+            std::env::set_var("GIT_DIR", base.join(git_dir));
+        }
+        if let Some(work_tree) = &work_tree {
+            std::env::set_var("GIT_WORK_TREE", base.join(work_tree));
+        }
+
+        Ok(App {
+            directory,
+            git_dir,
+            work_tree,
+        })
+    }
+
+    pub async fn commit(
+        &self,
+        base_message: Option<String>,
+        assume_yes: bool,
+        options: CommitOptions,
+        output: Output,
+    ) -> anyhow::Result<()> {
+        let confirmor = Confirmor::new(assume_yes)?;
+
+        // a dry run prints only the final message, so it can be piped into `git commit -F -`
+        let dry_run = options.dry_run;
+        let comment_generator = comment_generator::CommentGenerator::new(
+            Self::prompt_translator()?,
+            base_message,
+            output == Output::Text && !dry_run,
+        );
+        let committer = Committer::new(self.repository()?, confirmor, comment_generator, options)?;
+
+        let report = committer.commit().await?;
+        match output {
+            Output::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            Output::Text if dry_run => println!("{}", report.message),
+            Output::Text => {}
+        }
+
+        Ok(())
+    }
+
+    pub async fn split(
+        &self,
+        base_message: Option<String>,
+        assume_yes: bool,
+        options: CommitOptions,
+    ) -> anyhow::Result<()> {
+        let confirmor = Confirmor::new(assume_yes)?;
+        let comment_generator =
+            comment_generator::CommentGenerator::new(Self::prompt_translator()?, base_message, true);
+        let committer = Committer::new(self.repository()?, confirmor, comment_generator, options)?;
+
+        committer.split().await?;
+
+        Ok(())
     }
 
-    pub async fn commit(&self, base_message: Option<String>, assume_yes: bool) -> anyhow::Result<()> {
+    pub async fn reword(&self, range: &str, assume_yes: bool, options: CommitOptions) -> anyhow::Result<()> {
         let confirmor = Confirmor::new(assume_yes)?;
+        let comment_generator = comment_generator::CommentGenerator::new(Self::prompt_translator()?, None, false);
+        let committer = Committer::new(self.repository()?, confirmor, comment_generator, options)?;
+
+        committer.reword(range).await?;
 
+        Ok(())
+    }
+
+    /// Writes a pull request title and description to `file`, or streams it to stdout.
+    pub async fn pull_request(&self, base: &str, file: Option<&Path>) -> anyhow::Result<()> {
+        let comment_generator =
+            comment_generator::CommentGenerator::new(Self::prompt_translator()?, None, file.is_none());
+        let history = History::new(self.repository()?, comment_generator);
+
+        let pull_request = history.pull_request(base).await?;
+        if let Some(file) = file {
+            std::fs::write(file, format!("{}\n", pull_request.trim()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Streams release notes for `range` to stdout, or inserts them into the changelog at `insert`.
+    pub async fn changelog(&self, range: &str, format: changelog::Format, insert: Option<&Path>) -> anyhow::Result<()> {
+        let repository = self.repository()?;
+        let changelog_path = insert.map(|path| repository.workdir().unwrap_or(repository.path()).join(path));
+        // fail before asking the model when there is nothing to insert into
+        let existing = changelog_path.as_ref().map(std::fs::read_to_string).transpose()?;
+
+        let comment_generator =
+            comment_generator::CommentGenerator::new(Self::prompt_translator()?, None, insert.is_none());
+        let history = History::new(repository, comment_generator);
+
+        let notes = history.changelog(range, format).await?;
+        if let (Some(path), Some(existing)) = (changelog_path, existing) {
+            std::fs::write(&path, changelog::insert_unreleased(&existing, &notes))?;
+            println!("updated {}", path.display());
+        }
+
+        Ok(())
+    }
+
+    /// Non-interactive entry point of the installed prepare-commit-msg hook.
+    pub async fn prepare_commit_msg(&self, message_file: &Path, source: Option<&str>) -> anyhow::Result<()> {
+        if !Hooks::should_prepare(source) {
+            return Ok(());
+        }
+
+        let confirmor = Confirmor::new(true)?;
+        let comment_generator = comment_generator::CommentGenerator::new(Self::prompt_translator()?, None, false);
+        let committer = Committer::new(
+            self.repository()?,
+            confirmor,
+            comment_generator,
+            CommitOptions::default(),
+        )?;
+
+        committer.prepare_message_file(message_file).await
+    }
+
+    pub fn install_hook(&self) -> anyhow::Result<()> {
+        Hooks::new(&self.repository()?)?.install()
+    }
+
+    pub fn uninstall_hook(&self) -> anyhow::Result<()> {
+        Hooks::new(&self.repository()?)?.uninstall()
+    }
+
+    /// Opens the repository like git does: `--git-dir` or `$GIT_DIR` if given, otherwise discovered upward
+    /// from `-C` or the current directory, honoring `$GIT_WORK_TREE` and `$GIT_CEILING_DIRECTORIES`.
+    /// Linked worktrees are found through their `.git` file.
+    fn repository(&self) -> anyhow::Result<Repository> {
+        let directory = self.directory.as_deref().unwrap_or(Path::new("."));
+        let git_dir = self
+            .git_dir
+            .clone()
+            .or_else(|| std::env::var_os("GIT_DIR").map(PathBuf::from));
+        let start = git_dir
+            .map(|git_dir| directory.join(git_dir))
+            .unwrap_or(directory.to_path_buf());
+        let ceiling_dirs = std::env::var_os("GIT_CEILING_DIRECTORIES")
+            .map(|dirs| std::env::split_paths(&dirs).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        let repository = Repository::open_ext(start, RepositoryOpenFlags::FROM_ENV, ceiling_dirs)?;
+        if let Some(work_tree) = &self.work_tree {
+            repository.set_workdir(&directory.join(work_tree), false)?;
+        }
+
+        Ok(repository)
+    }
+
+    fn prompt_translator() -> anyhow::Result<PromptTranslator> {
         let prompt_translator = match config::get_model_provider()?.ok_or(UserError::ModelProviderNotSet)? {
             ModelProvider::OpenAI => PromptTranslator::OpenAI(OpenAITranslator::new(
                 config::get(config::Item::OpenaiModel)?.unwrap_or("gpt-4-turbo-preview".to_string()),
@@ -26,11 +192,6 @@ impl App {
             )),
         };
 
-        let comment_generator = comment_generator::CommentGenerator::new(prompt_translator, base_message);
-        let committer = Committer::new(confirmor, comment_generator)?;
-
-        committer.commit().await?;
-
-        Ok(())
+        Ok(prompt_translator)
     }
 }
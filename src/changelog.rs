@@ -0,0 +1,141 @@
+/// Layouts the release notes can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Sections named after the commit actions, e.g. `### Features`.
+    Markdown,
+    /// The `Added`/`Changed`/`Deprecated`/`Removed`/`Fixed`/`Security` sections of keepachangelog.com.
+    KeepAChangelog,
+}
+
+impl Format {
+    /// How the model should lay out the notes.
+    pub fn instructions(&self) -> &'static str {
+        match self {
+            Format::Markdown => {
+                r#"- Group the notes under `### ` headings named after the actions, e.g. `### Features`, `### Bug Fixes`, `### Documentation`.
+- Do not write a heading for the release itself."#
+            }
+            Format::KeepAChangelog => {
+                r#"- Follow the Keep a Changelog format: group the notes under `### Added`, `### Changed`, `### Deprecated`, `### Removed`, `### Fixed` and `### Security`, in this order.
+- Map `feat` to Added, `fix` to Fixed, and most other actions to Changed. Leave out sections without notes.
+- Do not write a heading for the release itself."#
+            }
+        }
+    }
+}
+
+/// Inserts `notes` at the top of the `Unreleased` section of `changelog`.
+/// The section is added before the first release when the changelog does not have one.
+pub fn insert_unreleased(changelog: &str, notes: &str) -> String {
+    let notes = notes.trim();
+    let lines = changelog.lines().collect::<Vec<_>>();
+
+    let unreleased = lines.iter().position(|line| is_unreleased_heading(line));
+    let (before, after, heading) = match unreleased {
+        Some(index) => (&lines[..=index], &lines[index + 1..], None),
+        None => {
+            let first_release = lines
+                .iter()
+                .position(|line| line.starts_with("## "))
+                .unwrap_or(lines.len());
+            (
+                &lines[..first_release],
+                &lines[first_release..],
+                Some("## [Unreleased]"),
+            )
+        }
+    };
+
+    let mut result = before.join("\n").trim_end().to_string();
+    if let Some(heading) = heading {
+        if !result.is_empty() {
+            result.push_str("\n\n");
+        }
+        result.push_str(heading);
+    }
+    result.push_str("\n\n");
+    result.push_str(notes);
+    result.push('\n');
+
+    let after = after.join("\n");
+    let after = after.trim_start_matches('\n');
+    if !after.is_empty() {
+        result.push('\n');
+        result.push_str(after);
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Matches `## [Unreleased]` as well as `## Unreleased`, in any case.
+fn is_unreleased_heading(line: &str) -> bool {
+    line.strip_prefix("## ")
+        .map(|title| {
+            title
+                .trim()
+                .trim_start_matches('[')
+                .to_lowercase()
+                .starts_with("unreleased")
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserts_at_top_of_unreleased_section() {
+        let changelog =
+            "# Changelog\n\n## [Unreleased]\n\n### Fixed\n\n- old fix\n\n## [1.0.0] - 2024-01-01\n\n- first\n";
+
+        assert_eq!(
+            insert_unreleased(changelog, "### Added\n\n- new flag\n"),
+            "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- new flag\n\n### Fixed\n\n- old fix\n\n## [1.0.0] - 2024-01-01\n\n- first\n"
+        );
+    }
+
+    #[test]
+    fn finds_unreleased_heading_in_any_case_and_without_brackets() {
+        assert!(is_unreleased_heading("## [Unreleased]"));
+        assert!(is_unreleased_heading("## Unreleased"));
+        assert!(is_unreleased_heading("## unreleased changes"));
+        assert!(!is_unreleased_heading("### Unreleased"));
+        assert!(!is_unreleased_heading("## [1.0.0]"));
+    }
+
+    #[test]
+    fn adds_unreleased_section_before_first_release() {
+        let changelog = "# Changelog\n\nAll notable changes.\n\n## [1.0.0] - 2024-01-01\n\n- first\n";
+
+        assert_eq!(
+            insert_unreleased(changelog, "- new flag"),
+            "# Changelog\n\nAll notable changes.\n\n## [Unreleased]\n\n- new flag\n\n## [1.0.0] - 2024-01-01\n\n- first\n"
+        );
+    }
+
+    #[test]
+    fn adds_unreleased_section_at_end_without_releases() {
+        assert_eq!(
+            insert_unreleased("# Changelog\n", "- new flag"),
+            "# Changelog\n\n## [Unreleased]\n\n- new flag\n"
+        );
+    }
+
+    #[test]
+    fn adds_unreleased_section_to_empty_changelog() {
+        assert_eq!(
+            insert_unreleased("", "- new flag\n\n"),
+            "## [Unreleased]\n\n- new flag\n"
+        );
+    }
+
+    #[test]
+    fn fills_empty_unreleased_section() {
+        assert_eq!(
+            insert_unreleased("## [Unreleased]\n\n## [1.0.0]\n", "- new flag"),
+            "## [Unreleased]\n\n- new flag\n\n## [1.0.0]\n"
+        );
+    }
+}
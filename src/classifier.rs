@@ -0,0 +1,96 @@
+use std::path::Path;
+
+/// Kinds of files whose patch text is replaced by a one-line description in the prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum FileKind {
+    #[display(fmt = "binary")]
+    Binary,
+    #[display(fmt = "generated")]
+    Generated,
+    #[display(fmt = "vendored")]
+    Vendored,
+    #[display(fmt = "snapshot")]
+    Snapshot,
+}
+
+const VENDORED_DIRS: &[&str] = &["vendor", "vendors", "third_party", "node_modules", "bower_components"];
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    ".pb.swift",
+    "_pb2.py",
+    "_pb2_grpc.py",
+    "_pb2.pyi",
+    ".g.dart",
+    ".freezed.dart",
+];
+const SNAPSHOT_SUFFIXES: &[&str] = &[".snap", ".snap.new", ".ambr"];
+
+/// Classifies a changed file by its `.gitattributes` first, then by well-known paths.
+/// `linguist-generated=false` and `linguist-vendored=false` opt a file back in.
+pub fn classify(repository: &git2::Repository, path: &Path, binary: bool) -> Option<FileKind> {
+    if binary {
+        return Some(FileKind::Binary);
+    }
+
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+
+    let generated = attribute(repository, path, "linguist-generated")
+        .unwrap_or_else(|| GENERATED_SUFFIXES.iter().any(|suffix| file_name.ends_with(suffix)));
+    if generated {
+        return Some(FileKind::Generated);
+    }
+
+    let vendored = attribute(repository, path, "linguist-vendored").unwrap_or_else(|| {
+        path.components()
+            .any(|component| VENDORED_DIRS.contains(&component.as_os_str().to_string_lossy().as_ref()))
+    });
+    if vendored {
+        return Some(FileKind::Vendored);
+    }
+
+    let snapshot = path
+        .components()
+        .any(|component| component.as_os_str() == "__snapshots__")
+        || SNAPSHOT_SUFFIXES.iter().any(|suffix| file_name.ends_with(suffix));
+    if snapshot {
+        return Some(FileKind::Snapshot);
+    }
+
+    None
+}
+
+/// Describes a classified file in one line, in place of its patch.
+pub fn describe(path: &str, kind: FileKind, status: git2::Delta, old_size: usize, new_size: usize) -> String {
+    let status = match status {
+        git2::Delta::Added => "added",
+        git2::Delta::Deleted => "deleted",
+        git2::Delta::Renamed => "renamed",
+        git2::Delta::Copied => "copied",
+        _ => "modified",
+    };
+
+    format!(
+        "# {} ({} file {}): {} -> {} bytes ({:+})\n",
+        path,
+        kind,
+        status,
+        old_size,
+        new_size,
+        new_size as i64 - old_size as i64
+    )
+}
+
+fn attribute(repository: &git2::Repository, path: &Path, name: &str) -> Option<bool> {
+    let value = repository
+        .get_attr(path, name, git2::AttrCheckFlags::INDEX_THEN_FILE)
+        .ok()?;
+
+    match git2::AttrValue::from_string(value) {
+        git2::AttrValue::True => Some(true),
+        git2::AttrValue::False => Some(false),
+        git2::AttrValue::String(value) => Some(value != "false"),
+        _ => None,
+    }
+}
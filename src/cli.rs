@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
 #[derive(Debug, Parser)]
@@ -5,16 +7,86 @@ pub struct Args {
     #[clap(subcommand)]
     pub command: Option<Command>,
 
+    #[clap(
+        short = 'C',
+        value_name = "PATH",
+        help = "Run as if huge-commit was started in <PATH>."
+    )]
+    pub directory: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Path to the repository's git directory. Defaults to $GIT_DIR."
+    )]
+    pub git_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        help = "Path to the working tree. Defaults to $GIT_WORK_TREE."
+    )]
+    pub work_tree: Option<PathBuf>,
+
     #[clap(short = 'm', long, help = "The base message to use for the commit.", num_args(1..))]
     pub base_message: Option<Vec<String>>,
 
+    #[clap(short = 'y', long, default_value = "false", help = "Assume yes to all prompts.")]
+    pub assume_yes: bool,
+
     #[clap(
-        short = 'y',
         long,
-        default_value = "false",
-        help = "Assume yes to all prompts."
+        help = "Regenerate the message for HEAD including newly staged changes, and rewrite HEAD."
     )]
-    pub assume_yes: bool,
+    pub amend: bool,
+
+    #[clap(
+        short = 'S',
+        long,
+        num_args(0..=1),
+        require_equals = true,
+        value_name = "KEYID",
+        help = "Sign the commit, optionally with the given key. Signs by default when commit.gpgsign is set."
+    )]
+    pub gpg_sign: Option<Option<String>>,
+
+    #[clap(short = 'n', long, help = "Bypass the pre-commit and commit-msg hooks.")]
+    pub no_verify: bool,
+
+    #[clap(
+        short = 'a',
+        long,
+        help = "Stage changes to tracked files before committing, like `git commit -a`."
+    )]
+    pub all: bool,
+
+    #[clap(long, help = "Stage untracked files too before committing.")]
+    pub include_untracked: bool,
+
+    #[clap(
+        long,
+        help = "Generate and print the message without staging, running hooks or committing."
+    )]
+    pub dry_run: bool,
+
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        help = "Generate N alternative messages and choose one."
+    )]
+    pub candidates: usize,
+
+    #[clap(long, value_enum, default_value_t = Output::Text, help = "How the commit is reported on stdout.")]
+    pub output: Output,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Output {
+    /// Stream the message as it is generated.
+    Text,
+    /// Print the message, model, changed files and token usage as one JSON object.
+    Json,
 }
 
 #[derive(Debug, Subcommand)]
@@ -22,6 +94,50 @@ pub enum Command {
     #[clap(about = "Commit changes.")]
     Commit,
 
+    #[clap(about = "Split staged changes into several commits.")]
+    Split,
+
+    #[clap(about = "Regenerate the messages of existing commits and rewrite them.")]
+    Reword {
+        #[clap(help = "Commits to reword, e.g. `main..HEAD`. A single revision means `<revision>..HEAD`.")]
+        range: String,
+    },
+
+    #[clap(about = "Generate a pull request title and description for the current branch.")]
+    Pr {
+        #[clap(
+            long,
+            default_value = "main",
+            help = "The branch the pull request will be merged into."
+        )]
+        base: String,
+
+        #[clap(short = 'f', long, help = "Write to this file instead of stdout.")]
+        file: Option<PathBuf>,
+    },
+
+    #[clap(about = "Generate release notes from the commits in a range.")]
+    Changelog {
+        #[clap(help = "Commits to describe, e.g. `v1.0.0..v1.1.0`. A single revision means `<revision>..HEAD`.")]
+        range: String,
+
+        #[clap(long, value_enum, default_value_t = crate::changelog::Format::Markdown)]
+        format: crate::changelog::Format,
+
+        #[clap(
+            long,
+            num_args(0..=1),
+            require_equals = true,
+            value_name = "FILE",
+            default_missing_value = "CHANGELOG.md",
+            help = "Insert the notes into the Unreleased section of an existing changelog, relative to the working tree."
+        )]
+        insert: Option<PathBuf>,
+    },
+
+    #[clap(subcommand, about = "Install or uninstall the prepare-commit-msg hook.")]
+    Hook(crate::hooks::Command),
+
     #[clap(subcommand, about = "Get or set configuration.")]
     Config(crate::config::Command),
 
@@ -1,21 +1,294 @@
+use std::cell::Cell;
+
+use crate::changelog;
+use crate::config;
+use crate::conventional;
+use crate::gitmoji;
+use crate::message;
 use crate::prompt_translator::PromptTranslator;
 use futures::StreamExt;
 use tokio::pin;
 
+/// How many summarization requests run at the same time.
+const SUMMARY_CONCURRENCY: usize = 4;
+/// Upper bound of a single summarization request, so map steps stay cheap even with large context windows.
+const MAX_CHUNK_TOKENS: usize = 16_000;
+/// Rough size of one file summary, used to decide how many files need summarizing.
+const SUMMARY_TOKENS: usize = 150;
+/// Most tokens a commit message or file summary may take.
+const MESSAGE_MAX_TOKENS: u32 = 1_024;
+/// Most tokens release notes, pull requests and split plans may take, the most every supported model can write.
+const LONG_MAX_TOKENS: u32 = 4_096;
+
+/// Facts about the commit being made that the diff alone does not tell the model.
+#[derive(Debug, Default, Clone)]
+pub struct CommitContext {
+    pub initial_commit: bool,
+    /// The message of the commit being amended.
+    pub previous_message: Option<String>,
+    /// Changed files left out of the prompt by `.hcignore`.
+    pub omitted_files: Vec<String>,
+    /// The commit concludes a merge, so it may explain how conflicts were resolved.
+    pub merge: bool,
+    /// The message git prepared in `MERGE_MSG` for the merge, cherry-pick or revert being concluded.
+    pub prepared_message: Option<String>,
+    /// Files that had conflicts, diffed from the merged-in commit to how they were resolved.
+    pub resolutions: Vec<FileDiff>,
+    /// A generated message the user asked to regenerate, or that broke the commit rules.
+    pub rejected_message: Option<String>,
+    /// What the user or the commit rules said was wrong with `rejected_message`.
+    pub feedback: Option<String>,
+    /// Conventional Commits scopes of the changed files, from `.hcscopes`.
+    pub scopes: Vec<String>,
+    /// The repository's commitlint rules, described by `commitlint::Rules::describe`.
+    pub lint_rules: Vec<String>,
+}
+
+const COMMIT_MESSAGE_RULES: &str = r#"- Limit the first line to a maximum of 10 words.
+- Explain the reason behind the changes.
+- Only provide the commit message without starting with "Commit message:".
+- If you can't fit everything in 10 words, prioritize the most important information.
+- Use present tense verbs, e.g., "Add feature" instead of "Added feature".
+- Do not write things that aren't related to the changes. Meaning, upgrading version of program itself does not means features is added or changed
+- For submodule updates, say what the new submodule commits bring in, e.g. "Bump vendor/foo to include retry fix"."#;
+
+const PLAIN_STYLE_RULES: &str = r#"basic comment message format is `verb` + subject + details`. you should not start with `action:` or `action(..):`. use normal sentence.
+you may choose action from following list. if you can't find suitable action, you can use other action or write your own action.
+"#;
+
+/// The action vocabulary commits are described with, shared by commit messages and changelogs.
+const COMMIT_ACTIONS: &str = r#"- `feat` new feature
+- `fix` bug fix
+- `docs` documentation
+- `refactor` A code change that neither fixes a bug nor adds a feature. also includes white space, formatting, missing semi-colons. never include new feature or bug fix. this is not preferred action. use other if possible.
+- `enhance` A code change that improves UX. like performance, messages. never include new feature or bug fix. this is not preferred action. use other if possible.
+- `test` Adding missing tests or correcting existing tests
+- `build` Changes that affect the build system, includes ci
+- `deps` dependency updates
+- or you can add your own action, if you can't find suitable action from above list."#;
+
+/// The patch text of one changed file, as it is put into the prompt.
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: String,
+    pub patch: String,
+}
+
+/// A numbered item of a split prompt: a hunk, or a whole file the model places without seeing its patch.
+#[derive(Debug, Clone)]
+pub struct SplitHunk {
+    /// `path`, or `path (what it is)` for whole files.
+    pub label: String,
+    pub patch: String,
+}
+
+/// One commit of a split plan, made of the numbered hunks the model grouped together.
+#[derive(Debug, serde::Deserialize)]
+pub struct PlannedCommit {
+    pub message: String,
+    pub hunks: Vec<usize>,
+}
+
+/// Estimated tokens sent to and received from the model, summed over every request.
+#[derive(Debug, Default, Clone, Copy, serde::Serialize)]
+pub struct TokenUsage {
+    pub prompt: usize,
+    pub completion: usize,
+}
+
 pub struct CommentGenerator {
     prompt_translator: PromptTranslator,
     base_message: Option<String>,
+    /// Print the commit message while it streams in.
+    echo: bool,
+    usage: Cell<TokenUsage>,
 }
 
 impl CommentGenerator {
-    pub fn new(prompt_translator: PromptTranslator, base_message: Option<String>) -> Self {
+    pub fn new(prompt_translator: PromptTranslator, base_message: Option<String>, echo: bool) -> Self {
         CommentGenerator {
             prompt_translator,
             base_message,
+            echo,
+            usage: Cell::new(TokenUsage::default()),
         }
     }
 
-    pub async fn gen_commit_message<'a>(&self, diff: String) -> anyhow::Result<String> {
+    pub fn provider(&self) -> &'static str {
+        self.prompt_translator.provider()
+    }
+
+    pub fn model(&self) -> &str {
+        self.prompt_translator.model()
+    }
+
+    pub fn usage(&self) -> TokenUsage {
+        self.usage.get()
+    }
+
+    /// Renders `files` for the commit message prompt, summarizing the largest ones when they don't fit the
+    /// token budget. The result is reused for candidates, retries and regenerations of the same commit.
+    pub async fn prompt_diff(&self, files: Vec<FileDiff>) -> anyhow::Result<String> {
+        self.fit_to_budget(files).await
+    }
+
+    /// Generates a message for `diff`, as rendered by `prompt_diff`.
+    pub async fn gen_commit_message(&self, diff: &str, context: &CommitContext) -> anyhow::Result<String> {
+        let prompt = self.commit_message_prompt(diff, context)?;
+        let message = self.complete(prompt, self.echo, MESSAGE_MAX_TOKENS).await?;
+
+        self.shape_message(&message, context, self.echo)
+    }
+
+    /// Generates a message without streaming it, e.g. to replace one that broke the commit rules.
+    pub async fn gen_commit_message_quietly(&self, diff: &str, context: &CommitContext) -> anyhow::Result<String> {
+        let prompt = self.commit_message_prompt(diff, context)?;
+        let message = self.complete(prompt, false, MESSAGE_MAX_TOKENS).await?;
+
+        self.shape_message(&message, context, false)
+    }
+
+    /// Generates `count` alternative messages for the same changes, requested concurrently.
+    /// A single message is streamed as usual, while several are only returned.
+    pub async fn gen_commit_messages(
+        &self,
+        diff: &str,
+        context: &CommitContext,
+        count: usize,
+    ) -> anyhow::Result<Vec<String>> {
+        if count <= 1 {
+            return Ok(vec![self.gen_commit_message(diff, context).await?]);
+        }
+
+        let prompt = self.commit_message_prompt(diff, context)?;
+        let candidates =
+            futures::future::try_join_all((0..count).map(|_| self.complete(prompt.clone(), false, MESSAGE_MAX_TOKENS)))
+                .await?;
+
+        candidates
+            .iter()
+            .map(|message| self.shape_message(message, context, false))
+            .collect()
+    }
+
+    /// Prints a message that was generated without streaming it, when `echo` is set.
+    pub fn print(&self, message: &str) {
+        if self.echo {
+            println!("{}", message.trim());
+        }
+    }
+
+    /// How the first line is formatted in the configured style. `scopes` are those of the changed files.
+    fn style_prompt(scopes: &[String]) -> anyhow::Result<String> {
+        let prompt = match config::get_style()? {
+            config::Style::Plain => format!("{PLAIN_STYLE_RULES}{COMMIT_ACTIONS}"),
+            config::Style::Conventional => {
+                let scope_rule = match scopes {
+                    [] => "`scope` is optional. Use a short name of the area of the code that changed, e.g. the module or package, or leave it out with its parentheses.".to_string(),
+                    [scope] => format!("Use `{}` as the scope.", scope),
+                    scopes => format!(
+                        "The changes span the scopes {}. Use the main one, or leave the scope out with its parentheses when no scope dominates.",
+                        scopes.join(", ")
+                    ),
+                };
+                format!(
+                    r#"Format the first line as `type(scope): description`, following the Conventional Commits specification, e.g. "fix(parser): handle empty input".
+- `type` is one of: {}.
+- {scope_rule}
+- The description starts with a lowercase verb, e.g. "add", "fix".
+- If the change breaks a public API or behavior users rely on, write `!` right before the colon, and end the message with a blank line and a `BREAKING CHANGE: <what breaks and how to migrate>` footer, even when the message is otherwise 1 line."#,
+                    conventional::TYPES.join(", ")
+                )
+            }
+            config::Style::Gitmoji => {
+                let actions = gitmoji::ACTION_GITMOJIS
+                    .iter()
+                    .map(|(action, code)| format!("- {} for {}", code, action))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let gitmojis = gitmoji::GITMOJIS
+                    .iter()
+                    .map(|(_, code, description)| format!("- {} {}", code, description))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    r#"Start the first line with the gitmoji shortcode that fits the change best, followed by a sentence starting with a capitalized verb, e.g. ":bug: Fix crash on empty input".
+Usually the gitmoji is one of:
+{actions}
+Otherwise pick it from the full list:
+{gitmojis}"#
+                )
+            }
+        };
+
+        Ok(prompt)
+    }
+
+    /// Lays out a generated message in the configured shape and style, and warns when its subject is too long.
+    /// When `echo` is set and the streamed text had to be changed, the result is shown again.
+    fn shape_message(&self, message: &str, context: &CommitContext, echo: bool) -> anyhow::Result<String> {
+        let mut shaped = message::tidy(message, Self::message_shape(context)?);
+
+        match config::get_style()? {
+            config::Style::Plain => {}
+            config::Style::Conventional => {
+                if let [scope] = context.scopes.as_slice() {
+                    shaped = conventional::set_scope(&shaped, scope);
+                }
+                // a subject-only shape would drop the footer semantic-release looks for
+                if conventional::breaking_change_footer(&shaped).is_none() {
+                    if let Some(footer) = conventional::breaking_change_footer(message) {
+                        shaped = format!("{}\n\n{}", shaped, footer);
+                    }
+                }
+            }
+            config::Style::Gitmoji => shaped = gitmoji::apply(&shaped, config::get_gitmoji_format()?),
+        }
+
+        let subject_length = Self::subject_length()?;
+        let subject = shaped.lines().next().unwrap_or("");
+        if subject.chars().count() > subject_length {
+            eprintln!(
+                "the subject is {} characters, over the limit of {}.",
+                subject.chars().count(),
+                subject_length
+            );
+        }
+
+        if echo && shaped != message.trim() {
+            eprintln!("reformatted the message:\n{}\n", shaped);
+        }
+
+        Ok(shaped)
+    }
+
+    /// The configured shape, except that a body is kept when the message builds on one: the amended
+    /// commit's, the one git prepared for a revert, or the explanation of how merge conflicts were resolved.
+    fn message_shape(context: &CommitContext) -> anyhow::Result<config::MessageShape> {
+        if context.merge || context.prepared_message.is_some() || context.previous_message.is_some() {
+            return Ok(config::MessageShape::SubjectAndBody);
+        }
+
+        config::get_message_shape()
+    }
+
+    fn subject_length() -> anyhow::Result<usize> {
+        Ok(config::get(config::Item::SubjectLength)?
+            .map(|length| length.parse())
+            .transpose()?
+            .unwrap_or(message::DEFAULT_SUBJECT_LENGTH))
+    }
+
+    fn commit_message_prompt(&self, diff: &str, context: &CommitContext) -> anyhow::Result<String> {
+        let mut diff = diff.to_string();
+        if !context.omitted_files.is_empty() {
+            diff.push_str(&format!(
+                "\n{} files omitted: {}\n",
+                context.omitted_files.len(),
+                context.omitted_files.join(", ")
+            ));
+        }
+
         let base_message_prompt = self
             .base_message
             .as_ref()
@@ -34,48 +307,590 @@ e.g. `rubocop -a` -> "Run `rubocop -a`"
             })
             .unwrap_or("".to_string());
 
+        let initial_commit_prompt = if context.initial_commit {
+            r#"
+This is the initial commit of the repository. There is no previous history, so describe what the project sets up.
+e.g. "Initialize Rust CLI project with config loader"
+"#
+        } else {
+            ""
+        };
+
+        let previous_message_prompt = context
+            .previous_message
+            .as_ref()
+            .map(|message| {
+                format!(
+                    r#"
+I'm amending a commit. this was its message. keep what still applies and update it to describe all of the changes.
+```previous commit message
+{}
+```
+"#,
+                    message.trim()
+                )
+            })
+            .unwrap_or("".to_string());
+
+        let prepared_message_prompt = context
+            .prepared_message
+            .as_ref()
+            .map(|message| {
+                format!(
+                    r#"
+git prepared this message for the commit. keep its first line and its references to other commits, e.g. "This reverts commit ...".
+```prepared commit message
+{}
+```
+"#,
+                    message.trim()
+                )
+            })
+            .unwrap_or("".to_string());
+
+        let merge_prompt = if context.merge {
+            let resolutions = context
+                .resolutions
+                .iter()
+                .map(|file| file.patch.as_str())
+                .collect::<String>();
+            let resolutions = if resolutions.is_empty() {
+                "There were no conflicts.".to_string()
+            } else {
+                format!(
+                    r#"These files had conflicts. This is how they differ from the merged-in commit after the resolution:
+```diff
+{}
+```"#,
+                    resolutions
+                )
+            };
+
+            format!(
+                r#"
+This commit concludes a merge. The diff is against the first parent.
+After the first line, you may write a blank line and a short body that explains how conflicts were resolved.
+{resolutions}
+"#
+            )
+        } else {
+            "".to_string()
+        };
+
+        let subject_length = Self::subject_length()?;
+        let shape_prompt = match (config::get_message_shape()?, Self::message_shape(context)?) {
+            (config::MessageShape::Subject, config::MessageShape::Subject) => format!(
+                r#"- return commit message in only 1 line.
+- Keep the line under {subject_length} characters."#
+            ),
+            (config::MessageShape::Subject, _) => format!(
+                r#"- Write a subject line under {subject_length} characters.
+- Only add a body, after a blank line and wrapped at {} columns, where the message below asks for one or already has one."#,
+                message::BODY_WIDTH
+            ),
+            (config::MessageShape::SubjectAndBody, _) => format!(
+                r#"- Write a subject line, then a blank line, then a body that explains why the change was made, in a few sentences or bullet points.
+- Keep the subject under {subject_length} characters and wrap the body at {} columns.
+- Leave the body out only when the change is trivial."#,
+                message::BODY_WIDTH
+            ),
+        };
+
+        let style_prompt = Self::style_prompt(&context.scopes)?;
+        let lint_rules_prompt = if context.lint_rules.is_empty() {
+            "".to_string()
+        } else {
+            format!(
+                "The message is checked with these commitlint rules, so it must pass them:\n- {}",
+                context.lint_rules.join("\n- ")
+            )
+        };
+
+        let rejected_message_prompt = context
+            .rejected_message
+            .as_ref()
+            .map(|message| {
+                let feedback = context
+                    .feedback
+                    .as_ref()
+                    .map(|feedback| format!("Here is what I want to be changed: {}", feedback))
+                    .unwrap_or("Write a different one.".to_string());
+                format!(
+                    r#"
+You wrote this message before, but I didn't use it. {}
+```rejected commit message
+{}
+```
+"#,
+                    feedback,
+                    message.trim()
+                )
+            })
+            .unwrap_or("".to_string());
+
         let prompt = format!(
             r#"
 Write a commit message for the changes I will write at the end of this message.
-- Limit the first line to a maximum of 10 words.
-- return commit message in only 1 line.
-- Explain the reason behind the changes.
-- Only provide the commit message without starting with "Commit message:".
-- If you can't fit everything in 10 words, prioritize the most important information.
-- Use present tense verbs, e.g., "Add feature" instead of "Added feature".
-- Do not write things that aren't related to the changes. Meaning, upgrading version of program itself does not means features is added or changed
-
-basic comment message format is `verb` + subject + details`. you should not start with `action:` or `action(..):`. use normal sentence.
-you may choose action from following list. if you can't find suitable action, you can use other action or write your own action.
-- `feat` new feature
-- `fix` bug fix
-- `docs` documentation
-- `refactor` A code change that neither fixes a bug nor adds a feature. also includes white space, formatting, missing semi-colons. never include new feature or bug fix. this is not preferred action. use other if possible.
-- `enhance` A code change that improves UX. like performance, messages. never include new feature or bug fix. this is not preferred action. use other if possible.
-- `test` Adding missing tests or correcting existing tests
-- `build` Changes that affect the build system, includes ci
-- `deps` dependency updates
-- or you can add your own action, if you can't find suitable action from above list.
+{shape_prompt}
+{COMMIT_MESSAGE_RULES}
+{style_prompt}
+{lint_rules_prompt}
 
+{initial_commit_prompt}
+{previous_message_prompt}
+{prepared_message_prompt}
+{merge_prompt}
 {base_message_prompt}
+{rejected_message_prompt}
+
+{diff}
+"#
+        );
+
+        Ok(prompt)
+    }
+
+    /// Writes a pull request title and markdown body from the branch's commit subjects and cumulative diff.
+    /// The title is on the first line, followed by a blank line and the body.
+    pub async fn gen_pull_request(
+        &self,
+        commits: Vec<String>,
+        files: Vec<FileDiff>,
+        omitted_files: Vec<String>,
+    ) -> anyhow::Result<String> {
+        let mut diff = self.fit_to_budget(files).await?;
+        if !omitted_files.is_empty() {
+            diff.push_str(&format!(
+                "\n{} files omitted: {}\n",
+                omitted_files.len(),
+                omitted_files.join(", ")
+            ));
+        }
+        let commits = commits
+            .iter()
+            .map(|subject| format!("- {}", subject))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let prompt = format!(
+            r#"
+Write a pull request for the branch whose commits and cumulative diff I will write at the end of this message.
+- The first line is the title. Limit it to a maximum of 10 words and use present tense verbs, e.g., "Add feature".
+- Then write a blank line, followed by the description in markdown with these sections:
+  - `## Summary`: what the pull request does and why, in 1 to 3 sentences.
+  - `## Notable changes`: a bullet list of the changes reviewers should know about.
+  - `## Testing`: how the changes can be verified, based on the tests and code in the diff.
+- Only provide the title and the description, without starting with "Title:".
+- Do not write things that aren't related to the changes.
+
+```commits
+{commits}
+```
 
-```diff
 {diff}
+"#
+        );
+
+        self.complete(prompt, self.echo, LONG_MAX_TOKENS).await
+    }
+
+    /// Writes user-facing release notes from the full messages of the commits in a release, oldest first.
+    pub async fn gen_changelog(&self, commits: Vec<String>, format: changelog::Format) -> anyhow::Result<String> {
+        let commits = commits
+            .iter()
+            .map(|message| format!("- {}", message.trim().replace('\n', "\n  ")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let format_instructions = format.instructions();
+
+        let prompt = format!(
+            r#"
+Write release notes for the commits I will write at the end of this message.
+- First classify each commit by one of the following actions, then group the notes by them.
+{COMMIT_ACTIONS}
+- Write for the users of the project, not its developers. Describe what changed for them, not how the code changed.
+- Merge commits that describe the same change into one note, and leave out commits users would not notice, such as refactors and tests.
+- Write one bullet point per note, using present tense verbs.
+{format_instructions}
+- Only provide the release notes in markdown, without any explanation.
+
+```commits
+{commits}
 ```
 "#
         );
 
-        let response_rx = self.prompt_translator.translate(prompt).await?;
+        self.complete(prompt, self.echo, LONG_MAX_TOKENS).await
+    }
+
+    /// Asks the model to group the numbered hunks into coherent commits.
+    /// When they exceed the token budget, the largest hunks are listed without their patch.
+    pub async fn gen_split_plan(&self, mut hunks: Vec<SplitHunk>) -> anyhow::Result<Vec<PlannedCommit>> {
+        let budget = self.token_budget()?;
+        let total = hunks.iter().map(|hunk| estimate_tokens(&hunk.patch)).sum::<usize>();
+        if total > budget {
+            eprintln!(
+                "hunks are about {} tokens, over the budget of {}. leaving out the largest ones.",
+                total, budget
+            );
+            let mut largest = (0..hunks.len()).collect::<Vec<_>>();
+            largest.sort_by_key(|&i| std::cmp::Reverse(estimate_tokens(&hunks[i].patch)));
+            let mut remaining = total;
+            for i in largest {
+                if remaining <= budget {
+                    break;
+                }
+                let hunk = &mut hunks[i];
+                remaining -= estimate_tokens(&hunk.patch);
+                // the header line still tells the model where the hunk is
+                let (header, content) = hunk.patch.split_once('\n').unwrap_or((&hunk.patch, ""));
+                hunk.patch = format!(
+                    "{}\n({} lines left out to fit the token budget)\n",
+                    header,
+                    content.lines().count()
+                );
+                remaining += estimate_tokens(&hunk.patch);
+            }
+        }
+
+        let hunks = hunks
+            .iter()
+            .enumerate()
+            .map(|(i, hunk)| format!("### hunk {}: {}\n{}", i + 1, hunk.label, hunk.patch))
+            .collect::<String>();
+
+        let style_prompt = Self::style_prompt(&[])?;
+        let prompt = format!(
+            r#"
+I will write numbered hunks of a diff at the end of this message. Group them into as few commits as makes sense, where each commit is one coherent change.
+- Every hunk must belong to exactly one commit.
+- Order the commits so that each one builds on the previous ones.
+- Write a commit message for each commit following the rules below.
+- Respond with only a JSON array, without any explanation. e.g. [{{"message": "Add retry to fetcher", "hunks": [1, 3]}}, {{"message": "Fix typo in README", "hunks": [2]}}]
+- return each commit message in only 1 line.
+
+{COMMIT_MESSAGE_RULES}
+{style_prompt}
+
+{hunks}
+"#
+        );
+
+        let response = match self.complete(prompt, false, LONG_MAX_TOKENS).await {
+            Err(err) if matches!(err.downcast_ref(), Some(crate::UserError::ResponseCutOff(_))) => {
+                return Err(crate::UserError::SplitPlanCutOff(LONG_MAX_TOKENS).into());
+            }
+            response => response?,
+        };
+        let json = response
+            .find('[')
+            .zip(response.rfind(']'))
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| &response[start..=end])
+            .ok_or_else(|| crate::UserError::InvalidSplitPlan(format!("no JSON array in `{}`", response.trim())))?;
+
+        Ok(serde_json::from_str(json).map_err(|err| crate::UserError::InvalidSplitPlan(err.to_string()))?)
+    }
+
+    /// Renders the diff for the prompt. When it exceeds the model's token budget,
+    /// the largest files are summarized separately and only their summaries are included.
+    async fn fit_to_budget(&self, files: Vec<FileDiff>) -> anyhow::Result<String> {
+        let budget = self.token_budget()?;
+        let total = files.iter().map(|file| estimate_tokens(&file.patch)).sum::<usize>();
+
+        if total <= budget {
+            let patches = files.into_iter().map(|file| file.patch).collect::<String>();
+            return Ok(format!("```diff\n{}\n```", patches));
+        }
+
+        // summarize the largest files until the rest fits
+        let mut files = files;
+        files.sort_by_key(|file| std::cmp::Reverse(estimate_tokens(&file.patch)));
+        let mut remaining = total;
+        let mut summarized_count = 0;
+        for file in &files {
+            if remaining <= budget {
+                break;
+            }
+            remaining = remaining - estimate_tokens(&file.patch) + SUMMARY_TOKENS;
+            summarized_count += 1;
+        }
+        let raw = files.split_off(summarized_count);
+        let mut summarized = files;
+
+        // with very many files even the summaries don't fit, so the smallest are dropped
+        let mut omitted = Vec::new();
+        while remaining > budget && !summarized.is_empty() {
+            let file = summarized.pop().expect("summarized is not empty");
+            remaining = remaining.saturating_sub(SUMMARY_TOKENS);
+            omitted.push(file.path);
+        }
+
+        eprintln!(
+            "diff is about {} tokens, over the budget of {}. summarizing {} files separately.",
+            total,
+            budget,
+            summarized.len()
+        );
+        if !omitted.is_empty() {
+            eprintln!(
+                "{} files omitted from the prompt: {}",
+                omitted.len(),
+                omitted.join(", ")
+            );
+        }
+
+        let chunk_budget = budget.min(MAX_CHUNK_TOKENS);
+        let summaries = futures::stream::iter(&summarized)
+            .map(|file| self.summarize_file(file, chunk_budget))
+            .buffered(SUMMARY_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut diff = String::new();
+        if !raw.is_empty() {
+            let patches = raw.into_iter().map(|file| file.patch).collect::<String>();
+            diff.push_str(&format!("```diff\n{}\n```\n", patches));
+        }
+        diff.push_str("These files were too large to include, here are summaries of their changes:\n");
+        for (file, summary) in summarized.iter().zip(summaries) {
+            diff.push_str(&format!("- {}: {}\n", file.path, summary.trim()));
+        }
+        if !omitted.is_empty() {
+            diff.push_str(&format!(
+                "{} more files changed: {}\n",
+                omitted.len(),
+                omitted.join(", ")
+            ));
+        }
+
+        Ok(diff)
+    }
+
+    /// Summarizes one file's patch, splitting it at hunk boundaries when it is larger than `chunk_budget`.
+    async fn summarize_file(&self, file: &FileDiff, chunk_budget: usize) -> anyhow::Result<String> {
+        let chunks = split_into_chunks(&file.patch, chunk_budget);
+        if chunks.iter().any(|chunk| chunk.truncated) {
+            eprintln!(
+                "{}: some hunks exceed {} tokens and were truncated.",
+                file.path, chunk_budget
+            );
+        }
+
+        let summaries = futures::stream::iter(chunks)
+            .map(|chunk| {
+                let prompt = format!(
+                    r#"
+Summarize the following part of a diff of `{}` in 1 to 3 short sentences.
+Focus on what changed in behavior and why, not on listing lines. Only provide the summary.
+
+```diff
+{}
+```
+"#,
+                    file.path, chunk.text
+                );
+
+                self.complete(prompt, false, MESSAGE_MAX_TOKENS)
+            })
+            .buffered(SUMMARY_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(summaries.join(" "))
+    }
+
+    fn token_budget(&self) -> anyhow::Result<usize> {
+        match config::get(config::Item::TokenBudget)? {
+            Some(budget) => Ok(budget.parse()?),
+            None => Ok(default_token_budget(self.prompt_translator.model())),
+        }
+    }
+
+    /// Sends `prompt` and collects the streamed response of at most `max_tokens`,
+    /// printing it as it arrives when `echo` is set. A response cut off at `max_tokens` is an error.
+    async fn complete(&self, prompt: String, echo: bool, max_tokens: u32) -> anyhow::Result<String> {
+        let prompt_tokens = estimate_tokens(&prompt);
+        let response_rx = self.prompt_translator.translate(prompt, max_tokens).await?;
         pin!(response_rx);
 
-        let mut commit_message = String::new();
+        let mut response = String::new();
         while let Some(chunk) = response_rx.next().await {
-            commit_message.push_str(&chunk.to_string());
-            print!("{}", chunk);
-            std::io::Write::flush(&mut std::io::stdout()).unwrap();
+            let chunk = chunk?;
+            response.push_str(&chunk);
+            if echo {
+                print!("{}", chunk);
+                std::io::Write::flush(&mut std::io::stdout()).unwrap();
+            }
+        }
+        if echo {
+            println!();
+        }
+
+        let usage = self.usage.get();
+        self.usage.set(TokenUsage {
+            prompt: usage.prompt + prompt_tokens,
+            completion: usage.completion + estimate_tokens(&response),
+        });
+
+        Ok(response)
+    }
+}
+
+/// Estimates the token count of `text`. About 4 bytes per token holds well enough for code and English.
+fn estimate_tokens(text: &str) -> usize {
+    text.len() / 4 + 1
+}
+
+/// How many tokens of diff a model is sent before it gets summarized, leaving room for the prompt and answer.
+fn default_token_budget(model: &str) -> usize {
+    if model.starts_with("claude") {
+        150_000
+    } else if model.starts_with("gpt-4o") || model.starts_with("gpt-4-turbo") || model.ends_with("-preview") {
+        96_000
+    } else if model.starts_with("gpt-4-32k") {
+        24_000
+    } else if model.starts_with("gpt-3.5-turbo") {
+        12_000
+    } else {
+        6_000
+    }
+}
+
+struct Chunk {
+    text: String,
+    truncated: bool,
+}
+
+/// Splits a file patch into chunks of at most `budget` tokens at hunk boundaries.
+/// A single hunk larger than `budget` is cut off.
+fn split_into_chunks(patch: &str, budget: usize) -> Vec<Chunk> {
+    let max_len = budget * 4;
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    let mut hunks = Vec::new();
+    let mut hunk = String::new();
+    for line in patch.split_inclusive('\n') {
+        if line.starts_with("H ") && !hunk.is_empty() {
+            hunks.push(std::mem::take(&mut hunk));
         }
-        println!();
+        hunk.push_str(line);
+    }
+    hunks.push(hunk);
+
+    for hunk in hunks {
+        if !current.is_empty() && current.len() + hunk.len() > max_len {
+            chunks.push(Chunk {
+                text: std::mem::take(&mut current),
+                truncated: false,
+            });
+        }
+
+        if hunk.len() > max_len {
+            let mut end = max_len;
+            while !hunk.is_char_boundary(end) {
+                end -= 1;
+            }
+            chunks.push(Chunk {
+                text: hunk[..end].to_string(),
+                truncated: true,
+            });
+        } else {
+            current.push_str(&hunk);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(Chunk {
+            text: current,
+            truncated: false,
+        });
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hunk(header: &str, lines: usize) -> String {
+        let mut hunk = format!("H {}\n", header);
+        for i in 0..lines {
+            hunk.push_str(&format!("+ line {:03}\n", i));
+        }
+
+        hunk
+    }
+
+    #[test]
+    fn keeps_small_patch_in_one_chunk() {
+        let patch = format!(
+            "F diff --git a/x b/x\n{}{}",
+            hunk("@@ -1 +1 @@", 2),
+            hunk("@@ -9 +9 @@", 2)
+        );
+
+        let chunks = split_into_chunks(&patch, 1_000);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, patch);
+        assert!(!chunks[0].truncated);
+    }
+
+    #[test]
+    fn splits_at_hunk_boundaries() {
+        let first = format!("F diff --git a/x b/x\n{}", hunk("@@ -1 +1 @@", 5));
+        let second = hunk("@@ -20 +20 @@", 5);
+        let third = hunk("@@ -40 +40 @@", 5);
+        let patch = format!("{}{}{}", first, second, third);
+        // room for two hunks but not three
+        let budget = (first.len() + second.len()).div_ceil(4);
+
+        let chunks = split_into_chunks(&patch, budget);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].text, format!("{}{}", first, second));
+        assert_eq!(chunks[1].text, third);
+        assert!(chunks
+            .iter()
+            .all(|chunk| !chunk.truncated && chunk.text.len() <= budget * 4));
+    }
+
+    #[test]
+    fn cuts_off_hunk_larger_than_budget() {
+        let small = hunk("@@ -1 +1 @@", 1);
+        let large = hunk("@@ -20 +20 @@", 100);
+        let patch = format!("{}{}{}", small, large, small);
+
+        let chunks = split_into_chunks(&patch, 25);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].text, small);
+        assert!(chunks[1].truncated);
+        assert_eq!(chunks[1].text, large[..100]);
+        assert_eq!(chunks[2].text, small);
+        assert!(!chunks[2].truncated);
+    }
+
+    #[test]
+    fn cuts_off_at_char_boundary() {
+        // the 16-byte budget ends in the middle of the first `é`
+        let patch = format!("H @@ -1 +1 @@\n+{}\n", "é".repeat(20));
+
+        let chunks = split_into_chunks(&patch, 4);
+
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].truncated);
+        assert_eq!(chunks[0].text, "H @@ -1 +1 @@\n+");
+    }
 
-        Ok(commit_message)
+    #[test]
+    fn empty_patch_has_no_chunks() {
+        assert!(split_into_chunks("", 100).is_empty());
     }
 }
@@ -0,0 +1,718 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config;
+use crate::conventional::Header;
+
+/// Times a message that breaks the rules is regenerated, unless `lint-retries` is set.
+pub const DEFAULT_RETRIES: usize = 2;
+
+/// Where commitlint looks for its configuration, in the order it looks. `package.json` is checked first.
+const CONFIG_FILES: &[&str] = &[
+    ".commitlintrc",
+    ".commitlintrc.json",
+    ".commitlintrc.yaml",
+    ".commitlintrc.yml",
+    ".commitlintrc.js",
+    ".commitlintrc.cjs",
+    ".commitlintrc.mjs",
+    ".commitlintrc.ts",
+    "commitlint.config.js",
+    "commitlint.config.cjs",
+    "commitlint.config.mjs",
+    "commitlint.config.ts",
+];
+
+/// One-word trailer tokens. Multi-word ones like `Signed-off-by` are told from prose by their hyphens.
+const TRAILER_WORDS: &[&str] = &[
+    "Bug", "Cc", "Closes", "Fixes", "Issue", "Link", "Refs", "Resolves", "See",
+];
+
+/// The rules of `@commitlint/config-conventional`, the only shared configuration `extends` can name.
+const CONFIG_CONVENTIONAL: &str = r#"{
+    "body-leading-blank": [1, "always"],
+    "body-max-line-length": [2, "always", 100],
+    "footer-leading-blank": [1, "always"],
+    "footer-max-line-length": [2, "always", 100],
+    "header-max-length": [2, "always", 100],
+    "header-trim": [2, "always"],
+    "subject-case": [2, "never", ["sentence-case", "start-case", "pascal-case", "upper-case"]],
+    "subject-empty": [2, "never"],
+    "subject-full-stop": [2, "never", "."],
+    "type-case": [2, "always", "lower-case"],
+    "type-empty": [2, "never"],
+    "type-enum": [2, "always", ["build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test"]]
+}"#;
+
+/// A commitlint configuration, as written in `.commitlintrc.json` or the `commitlint` section of the config.
+#[derive(Debug, Default, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Config {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Extends>,
+    /// `rule-name = [level, "always" | "never", value]`, where level 0 disables, 1 warns and 2 fails.
+    #[serde(default)]
+    pub rules: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub enum Extends {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// A rule a message breaks.
+#[derive(Debug)]
+pub struct Violation {
+    pub rule: String,
+    /// Whether the rule is an error rather than a warning.
+    pub error: bool,
+    pub message: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}]", self.message, self.rule)
+    }
+}
+
+/// The rules commit messages of a repository are checked with, with `extends` resolved.
+#[derive(Debug, Default)]
+pub struct Rules {
+    rules: BTreeMap<String, serde_json::Value>,
+}
+
+impl Rules {
+    /// Reads the commitlint configuration of the repository at `workdir`, or the `commitlint` section of
+    /// the config when the repository has none. JavaScript configurations are evaluated with `node` when
+    /// `commitlint-js` allows it.
+    pub fn load(workdir: &Path) -> anyhow::Result<Self> {
+        let config = match read_repository_config(workdir)? {
+            Some(config) => config,
+            None => config::get_commitlint()?.unwrap_or_default(),
+        };
+
+        let extends = match config.extends {
+            Some(Extends::One(name)) => vec![name],
+            Some(Extends::Many(names)) => names,
+            None => vec![],
+        };
+        let mut rules = BTreeMap::new();
+        for name in extends {
+            if name.ends_with("config-conventional") {
+                rules.extend(serde_json::from_str::<BTreeMap<_, _>>(CONFIG_CONVENTIONAL)?);
+            } else {
+                eprintln!(
+                    "ignoring `extends: {}`, only @commitlint/config-conventional is known.",
+                    name
+                );
+            }
+        }
+        rules.extend(config.rules);
+
+        Ok(Rules { rules })
+    }
+
+    /// The enabled rules, one `name: always|never value` line each, for the model to follow.
+    pub fn describe(&self) -> Vec<String> {
+        self.rules
+            .keys()
+            .filter_map(|name| self.rule(name))
+            .map(|rule| match rule.value {
+                Some(value) => format!("{}: {} {}", rule.name, rule.when(), value),
+                None => format!("{}: {}", rule.name, rule.when()),
+            })
+            .collect()
+    }
+
+    /// Checks `message` against every enabled rule. Rules huge-commit doesn't know are skipped.
+    pub fn lint(&self, message: &str) -> Vec<Violation> {
+        let parts = Parts::parse(message);
+
+        self.rules
+            .keys()
+            .filter_map(|name| self.rule(name))
+            .filter_map(|rule| {
+                let (part, check) = rule.name.split_once('-')?;
+                let text = parts.get(part)?;
+                let problem = rule.check(check, text, parts.leading_blank(part))?;
+
+                Some(Violation {
+                    rule: rule.name.to_string(),
+                    error: rule.level >= 2,
+                    message: format!("{} {}", part, problem),
+                })
+            })
+            .collect()
+    }
+
+    fn rule<'a>(&'a self, name: &'a str) -> Option<Rule<'a>> {
+        let config = self.rules.get(name)?.as_array()?;
+        let level = config.first()?.as_u64()?;
+        if level == 0 {
+            return None;
+        }
+
+        Some(Rule {
+            name,
+            level,
+            always: config.get(1).and_then(|when| when.as_str()) != Some("never"),
+            value: config.get(2),
+        })
+    }
+}
+
+struct Rule<'a> {
+    name: &'a str,
+    level: u64,
+    always: bool,
+    value: Option<&'a serde_json::Value>,
+}
+
+impl Rule<'_> {
+    fn when(&self) -> &'static str {
+        if self.always {
+            "always"
+        } else {
+            "never"
+        }
+    }
+
+    fn must(&self) -> &'static str {
+        if self.always {
+            "must"
+        } else {
+            "must not"
+        }
+    }
+
+    /// What is wrong with `text` under the `check` half of the rule name, e.g. `max-length`.
+    fn check(&self, check: &str, text: &str, leading_blank: bool) -> Option<String> {
+        let length = text.chars().count();
+        let number = self.value.and_then(|value| value.as_u64()).map(|n| n as usize);
+        let strings = match self.value {
+            Some(serde_json::Value::String(value)) => vec![value.as_str()],
+            Some(serde_json::Value::Array(values)) => values.iter().filter_map(|value| value.as_str()).collect(),
+            _ => vec![],
+        };
+
+        match check {
+            "empty" if text.is_empty() != self.always => Some(format!("{} be empty", self.must())),
+            "enum" if !text.is_empty() && !strings.is_empty() => {
+                // several scopes may be given as `a,b` or `a/b`
+                let all_listed = text.split([',', '/']).all(|item| strings.contains(&item.trim()));
+                (all_listed != self.always).then(|| format!("{} be one of [{}]", self.must(), strings.join(", ")))
+            }
+            "case" if !text.is_empty() => {
+                let matches = strings.iter().any(|case| is_case(text, case));
+                (matches != self.always).then(|| format!("{} be {}", self.must(), strings.join(", ")))
+            }
+            "max-length" => number.filter(|max| length > *max).map(|max| {
+                format!(
+                    "must not be longer than {} characters, current length is {}",
+                    max, length
+                )
+            }),
+            "min-length" if !text.is_empty() => number.filter(|min| length < *min).map(|min| {
+                format!(
+                    "must not be shorter than {} characters, current length is {}",
+                    min, length
+                )
+            }),
+            "max-line-length" => number
+                .filter(|max| text.lines().any(|line| line.chars().count() > *max))
+                .map(|max| format!("lines must not be longer than {} characters", max)),
+            "full-stop" if !text.is_empty() => {
+                let stop = strings.first().copied().unwrap_or(".");
+                (text.trim_end().ends_with(stop) != self.always).then(|| format!("{} end with `{}`", self.must(), stop))
+            }
+            "leading-blank" if !text.is_empty() && leading_blank != self.always => {
+                Some(format!("{} have a leading blank line", self.must()))
+            }
+            "trim" if text != text.trim() => Some("must not be surrounded by whitespace".to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// The parts of a message that rule names start with.
+struct Parts<'a> {
+    header: &'a str,
+    kind: &'a str,
+    scope: &'a str,
+    subject: &'a str,
+    body: String,
+    footer: String,
+    /// Whether the header is followed by a blank line.
+    leading_blank: bool,
+}
+
+impl<'a> Parts<'a> {
+    fn parse(message: &'a str) -> Self {
+        let message = message.trim_end();
+        let (header, rest) = message.split_once('\n').unwrap_or((message, ""));
+        let (kind, scope, subject) = match Header::parse(header.trim()) {
+            Ok(parsed) => (parsed.kind, parsed.scope.unwrap_or(""), parsed.description),
+            Err(_) => ("", "", ""),
+        };
+
+        let (leading_blank, rest) = match rest.split_once('\n') {
+            Some((first, rest)) if first.trim().is_empty() => (true, rest),
+            _ => (rest.trim().is_empty(), rest),
+        };
+        let paragraphs = rest.split("\n\n").collect::<Vec<_>>();
+        let footer_start = paragraphs
+            .iter()
+            .rposition(|paragraph| !paragraph.lines().all(is_trailer))
+            .map_or(0, |last_body| last_body + 1);
+
+        Parts {
+            header,
+            kind,
+            scope,
+            subject,
+            body: paragraphs[..footer_start].join("\n\n").trim().to_string(),
+            footer: paragraphs[footer_start..].join("\n\n").trim().to_string(),
+            leading_blank,
+        }
+    }
+
+    fn get(&self, part: &str) -> Option<&str> {
+        match part {
+            "header" => Some(self.header),
+            "type" => Some(self.kind),
+            "scope" => Some(self.scope),
+            "subject" => Some(self.subject),
+            "body" => Some(&self.body),
+            "footer" => Some(&self.footer),
+            _ => None,
+        }
+    }
+
+    /// Whether `part` follows a blank line. A footer is split from the body at one, but may follow the header.
+    fn leading_blank(&self, part: &str) -> bool {
+        match part {
+            "body" => self.leading_blank,
+            "footer" => self.leading_blank || !self.body.is_empty(),
+            _ => true,
+        }
+    }
+}
+
+/// `Token-name: value`, `Token #value` or `BREAKING CHANGE: value`, as in git trailers and issue references.
+/// A one-word `Token: value` only counts when the token is a known trailer, so prose like `Note: ...` stays in the body.
+fn is_trailer(line: &str) -> bool {
+    if line.starts_with("BREAKING CHANGE: ") {
+        return true;
+    }
+
+    let token_end = line.find([':', ' ']).unwrap_or(0);
+    let token = &line[..token_end];
+    if token.is_empty() || !token.chars().all(|c| c.is_alphanumeric() || c == '-') {
+        return false;
+    }
+
+    line[token_end..].starts_with(" #")
+        || (line[token_end..].starts_with(": ")
+            && (token.contains('-') || TRAILER_WORDS.iter().any(|word| word.eq_ignore_ascii_case(token))))
+}
+
+/// Whether `text` is written in one of commitlint's cases. Text without letters is in every case.
+fn is_case(text: &str, case: &str) -> bool {
+    if !text.chars().any(char::is_alphabetic) {
+        return true;
+    }
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let starts_upper = |word: &str| word.chars().next().is_some_and(|c| !c.is_lowercase());
+
+    match case {
+        "lower-case" | "lowercase" => text.to_lowercase() == text,
+        "upper-case" | "uppercase" => text.to_uppercase() == text,
+        "sentence-case" | "sentencecase" => starts_upper(text),
+        "start-case" | "startcase" => words.iter().all(|word| starts_upper(word)),
+        "pascal-case" | "pascalcase" => starts_upper(text) && text.chars().all(char::is_alphanumeric),
+        "camel-case" | "camelcase" => !starts_upper(text) && text.chars().all(char::is_alphanumeric),
+        "kebab-case" | "kebabcase" => text.chars().all(|c| c.is_lowercase() || c.is_ascii_digit() || c == '-'),
+        "snake-case" | "snakecase" => text.chars().all(|c| c.is_lowercase() || c.is_ascii_digit() || c == '_'),
+        _ => true,
+    }
+}
+
+/// The first commitlint configuration found at the root of the working tree.
+fn read_repository_config(workdir: &Path) -> anyhow::Result<Option<Config>> {
+    let invalid = |path: &Path, reason: String| {
+        anyhow::Error::from(crate::UserError::InvalidCommitlintConfig(
+            path.display().to_string(),
+            reason,
+        ))
+    };
+
+    let package_json = workdir.join("package.json");
+    if package_json.exists() {
+        let package = serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(&package_json)?)
+            .map_err(|e| invalid(&package_json, e.to_string()))?;
+        if let Some(config) = package.get("commitlint") {
+            return Ok(Some(
+                serde_json::from_value(config.clone()).map_err(|e| invalid(&package_json, e.to_string()))?,
+            ));
+        }
+    }
+
+    for file in CONFIG_FILES {
+        let path = workdir.join(file);
+        if !path.exists() {
+            continue;
+        }
+
+        let config = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ts") => {
+                eprintln!(
+                    "ignoring {}, TypeScript configurations are not supported. Use JSON or JavaScript instead.",
+                    file
+                );
+                continue;
+            }
+            Some("js" | "cjs" | "mjs") if !config::get_commitlint_js()? => {
+                // the file comes with the checkout, so running it would run code from whoever wrote the repository
+                eprintln!(
+                    "ignoring {}, JavaScript configurations are only run with `huge-commit config set commitlint-js true`.",
+                    file
+                );
+                continue;
+            }
+            Some("js" | "cjs" | "mjs") => serde_json::from_str(&evaluate(&path, workdir)?).map_err(|e| e.to_string()),
+            Some("json") => serde_json::from_str(&std::fs::read_to_string(&path)?).map_err(|e| e.to_string()),
+            // YAML is a superset of JSON
+            _ => serde_yaml::from_str(&std::fs::read_to_string(&path)?).map_err(|e| e.to_string()),
+        };
+
+        return config.map(Some).map_err(|reason| invalid(&path, reason));
+    }
+
+    Ok(None)
+}
+
+/// Runs a JavaScript configuration with `node` and returns what it exports as JSON.
+fn evaluate(path: &Path, workdir: &Path) -> anyhow::Result<String> {
+    const SCRIPT: &str = "import(require('url').pathToFileURL(process.argv[1]))
+        .then(async (module) => console.log(JSON.stringify(await (module.default ?? module))))";
+
+    let invalid = |reason: String| crate::UserError::InvalidCommitlintConfig(path.display().to_string(), reason);
+    let output = Command::new("node")
+        .arg("-e")
+        .arg(SCRIPT)
+        .arg(path)
+        .current_dir(workdir)
+        .output()
+        .map_err(|e| invalid(format!("node could not be run: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // node prints the offending source before the error itself
+        let error = stderr
+            .lines()
+            .find(|line| line.contains("Error"))
+            .unwrap_or(stderr.trim());
+        return Err(invalid(error.to_string()).into());
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(json: &str) -> Rules {
+        Rules {
+            rules: serde_json::from_str(json).unwrap(),
+        }
+    }
+
+    fn check(rule: serde_json::Value, check: &str, text: &str) -> Option<String> {
+        let config = rule.as_array().unwrap();
+        let rule = Rule {
+            name: "test",
+            level: 2,
+            always: config[1] != "never",
+            value: config.get(2),
+        };
+
+        rule.check(check, text, true)
+    }
+
+    /// A directory under the system temp dir holding `files`, removed again by the caller.
+    fn workdir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("huge-commit-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn parse_splits_header() {
+        let parts = Parts::parse("feat(api,cli)!: add a flag");
+
+        assert_eq!(parts.header, "feat(api,cli)!: add a flag");
+        assert_eq!(parts.kind, "feat");
+        assert_eq!(parts.scope, "api,cli");
+        assert_eq!(parts.subject, "add a flag");
+        assert_eq!(parts.body, "");
+        assert_eq!(parts.footer, "");
+    }
+
+    #[test]
+    fn parse_leaves_non_conventional_header_parts_empty() {
+        let parts = Parts::parse("Add a flag");
+
+        assert_eq!(parts.header, "Add a flag");
+        assert_eq!(parts.kind, "");
+        assert_eq!(parts.scope, "");
+        assert_eq!(parts.subject, "");
+    }
+
+    #[test]
+    fn parse_splits_trailers_into_footer() {
+        let parts = Parts::parse(
+            "fix: handle empty input\n\nThe parser panicked.\n\nSecond paragraph.\n\nRefs #12\nSigned-off-by: A <a@example.com>\n",
+        );
+
+        assert_eq!(parts.body, "The parser panicked.\n\nSecond paragraph.");
+        assert_eq!(parts.footer, "Refs #12\nSigned-off-by: A <a@example.com>");
+        assert!(parts.leading_blank);
+    }
+
+    #[test]
+    fn parse_treats_breaking_change_as_footer() {
+        let parts = Parts::parse("feat: drop v1\n\nOld clients stop working.\n\nBREAKING CHANGE: v1 is gone");
+
+        assert_eq!(parts.body, "Old clients stop working.");
+        assert_eq!(parts.footer, "BREAKING CHANGE: v1 is gone");
+    }
+
+    #[test]
+    fn parse_keeps_prose_with_colon_in_body() {
+        let parts =
+            Parts::parse("fix: retry uploads\n\nUploads failed on slow links.\n\nNote: the timeout is unchanged.");
+
+        assert_eq!(
+            parts.body,
+            "Uploads failed on slow links.\n\nNote: the timeout is unchanged."
+        );
+        assert_eq!(parts.footer, "");
+    }
+
+    #[test]
+    fn parse_detects_missing_leading_blank() {
+        let parts = Parts::parse("fix: retry uploads\nUploads failed on slow links.");
+
+        assert!(!parts.leading_blank);
+        assert_eq!(parts.body, "Uploads failed on slow links.");
+        assert!(!parts.leading_blank("body"));
+    }
+
+    #[test]
+    fn footer_right_after_header_has_leading_blank_only_with_blank_line() {
+        let parts = Parts::parse("fix: retry uploads\n\nCloses #3");
+
+        assert_eq!(parts.body, "");
+        assert_eq!(parts.footer, "Closes #3");
+        assert!(parts.leading_blank("footer"));
+    }
+
+    #[test]
+    fn is_trailer_accepts_trailers_and_references() {
+        assert!(is_trailer("Signed-off-by: A <a@example.com>"));
+        assert!(is_trailer("Co-authored-by: B <b@example.com>"));
+        assert!(is_trailer("Closes: #4"));
+        assert!(is_trailer("fixes #4"));
+        assert!(is_trailer("BREAKING CHANGE: v1 is gone"));
+        assert!(is_trailer("BREAKING-CHANGE: v1 is gone"));
+    }
+
+    #[test]
+    fn is_trailer_rejects_prose() {
+        assert!(!is_trailer("Note: the timeout is unchanged."));
+        assert!(!is_trailer("Before: it panicked"));
+        assert!(!is_trailer("The parser panicked."));
+        assert!(!is_trailer("two words: value"));
+        assert!(!is_trailer(": value"));
+    }
+
+    #[test]
+    fn check_max_length() {
+        let rule = serde_json::json!([2, "always", 10]);
+
+        assert_eq!(check(rule.clone(), "max-length", "0123456789"), None);
+        assert_eq!(
+            check(rule, "max-length", "0123456789a").as_deref(),
+            Some("must not be longer than 10 characters, current length is 11")
+        );
+    }
+
+    #[test]
+    fn check_max_line_length_counts_each_line() {
+        let rule = serde_json::json!([2, "always", 5]);
+
+        assert_eq!(check(rule.clone(), "max-line-length", "12345\n12345"), None);
+        assert!(check(rule, "max-line-length", "12345\n123456").is_some());
+    }
+
+    #[test]
+    fn check_empty() {
+        assert!(check(serde_json::json!([2, "never"]), "empty", "").is_some());
+        assert_eq!(check(serde_json::json!([2, "never"]), "empty", "x"), None);
+        assert!(check(serde_json::json!([2, "always"]), "empty", "x").is_some());
+    }
+
+    #[test]
+    fn check_enum_accepts_several_listed_scopes() {
+        let rule = serde_json::json!([2, "always", ["api", "cli"]]);
+
+        assert_eq!(check(rule.clone(), "enum", "api"), None);
+        assert_eq!(check(rule.clone(), "enum", "api,cli"), None);
+        assert_eq!(check(rule.clone(), "enum", "api/cli"), None);
+        assert_eq!(check(rule.clone(), "enum", ""), None);
+        assert_eq!(
+            check(rule, "enum", "api,web").as_deref(),
+            Some("must be one of [api, cli]")
+        );
+    }
+
+    #[test]
+    fn check_case_never() {
+        let rule = serde_json::json!([2, "never", ["sentence-case", "upper-case"]]);
+
+        assert_eq!(check(rule.clone(), "case", "add a flag"), None);
+        assert_eq!(
+            check(rule, "case", "Add a flag").as_deref(),
+            Some("must not be sentence-case, upper-case")
+        );
+    }
+
+    #[test]
+    fn check_full_stop() {
+        let never = serde_json::json!([2, "never", "."]);
+
+        assert_eq!(check(never.clone(), "full-stop", "add a flag"), None);
+        assert_eq!(
+            check(never, "full-stop", "add a flag.").as_deref(),
+            Some("must not end with `.`")
+        );
+        assert!(check(serde_json::json!([2, "always", "."]), "full-stop", "add a flag").is_some());
+    }
+
+    #[test]
+    fn check_trim() {
+        let rule = serde_json::json!([2, "always"]);
+
+        assert_eq!(check(rule.clone(), "trim", "fix: x"), None);
+        assert!(check(rule, "trim", " fix: x").is_some());
+    }
+
+    #[test]
+    fn check_skips_unknown_checks() {
+        assert_eq!(
+            check(serde_json::json!([2, "always", 1]), "exclamation-mark", "x"),
+            None
+        );
+    }
+
+    #[test]
+    fn is_case_matches_commitlint_cases() {
+        assert!(is_case("add a flag", "lower-case"));
+        assert!(!is_case("Add a flag", "lower-case"));
+        assert!(is_case("ADD A FLAG", "upper-case"));
+        assert!(is_case("Add a flag", "sentence-case"));
+        assert!(!is_case("add a flag", "sentence-case"));
+        assert!(is_case("Add A Flag", "start-case"));
+        assert!(!is_case("Add a Flag", "start-case"));
+        assert!(is_case("AddFlag", "pascal-case"));
+        assert!(is_case("addFlag", "camel-case"));
+        assert!(is_case("add-flag", "kebab-case"));
+        assert!(!is_case("add_flag", "kebab-case"));
+        assert!(is_case("add_flag", "snake-case"));
+    }
+
+    #[test]
+    fn is_case_holds_for_text_without_letters() {
+        assert!(is_case("1.2.3", "upper-case"));
+        assert!(is_case("1.2.3", "sentence-case"));
+    }
+
+    #[test]
+    fn lint_reports_enabled_rules_only() {
+        let rules = rules(
+            r#"{
+                "header-max-length": [2, "always", 20],
+                "subject-full-stop": [1, "never", "."],
+                "body-leading-blank": [0, "always"]
+            }"#,
+        );
+
+        let violations = rules.lint("fix: handle the empty input.\nbody");
+
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].rule, "header-max-length");
+        assert!(violations[0].error);
+        assert_eq!(violations[1].rule, "subject-full-stop");
+        assert!(!violations[1].error);
+        assert_eq!(
+            violations[1].to_string(),
+            "subject must not end with `.` [subject-full-stop]"
+        );
+    }
+
+    #[test]
+    fn describe_lists_enabled_rules() {
+        let rules = rules(
+            r#"{"type-enum": [2, "always", ["feat", "fix"]], "body-empty": [0, "never"], "header-trim": [2, "always"]}"#,
+        );
+
+        assert_eq!(
+            rules.describe(),
+            vec!["header-trim: always", r#"type-enum: always ["feat","fix"]"#]
+        );
+    }
+
+    #[test]
+    fn load_resolves_config_conventional_and_overrides_its_rules() {
+        let dir = workdir(
+            "extends",
+            &[(
+                ".commitlintrc.json",
+                r#"{"extends": ["@commitlint/config-conventional"], "rules": {"header-max-length": [2, "always", 50]}}"#,
+            )],
+        );
+
+        let rules = Rules::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rules.rules["header-max-length"], serde_json::json!([2, "always", 50]));
+        assert_eq!(rules.rules["subject-empty"], serde_json::json!([2, "never"]));
+        assert!(rules
+            .lint("chore: Update deps")
+            .iter()
+            .any(|v| v.rule == "subject-case"));
+        assert!(rules.lint("wip: update deps").iter().any(|v| v.rule == "type-enum"));
+    }
+
+    #[test]
+    fn load_ignores_unknown_shared_configs() {
+        let dir = workdir(
+            "unknown-extends",
+            &[(
+                "package.json",
+                r#"{"commitlint": {"extends": "@company/commitlint-config", "rules": {"scope-empty": [2, "never"]}}}"#,
+            )],
+        );
+
+        let rules = Rules::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rules.rules.keys().collect::<Vec<_>>(), vec!["scope-empty"]);
+    }
+}
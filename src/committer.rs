@@ -1,152 +1,1280 @@
-use crate::comment_generator::CommentGenerator;
+use crate::classifier::{self, FileKind};
+use crate::comment_generator::{CommentGenerator, CommitContext, FileDiff, SplitHunk, TokenUsage};
+use crate::commitlint::{self, Rules};
+use crate::config;
 use crate::confirmor::Confirmor;
-use crate::prompt_translator::PromptTranslator;
+use crate::conventional::{self, ScopeMap};
+use crate::editor;
+use crate::gitmoji;
+use crate::hcignore;
+use crate::history;
+use crate::hooks::Hooks;
+use crate::hunk::{self, HunkSelection};
+use crate::signer::Signer;
+use crate::submodule;
 
+use std::fmt::Write;
 use std::io::Read;
 use std::path::Path;
-use std::{fmt::Write, io::BufRead};
 
-use git2::{DiffFormat, DiffOptions, Repository};
+use git2::{ApplyLocation, ApplyOptions, DiffFindOptions, DiffFormat, DiffOptions, Repository};
+
+#[derive(Debug, Default)]
+pub struct CommitOptions {
+    /// Rewrite HEAD instead of creating a new commit on top of it.
+    pub amend: bool,
+    /// `-S`: sign the commit, optionally with the given key instead of `user.signingkey`.
+    pub gpg_sign: Option<Option<String>>,
+    /// Bypass the pre-commit and commit-msg hooks.
+    pub no_verify: bool,
+    /// `-a`: stage changes to tracked files before committing, like `git commit -a`.
+    pub all: bool,
+    /// Stage untracked files too before committing.
+    pub include_untracked: bool,
+    /// Generate the message without touching the index, running hooks or committing.
+    pub dry_run: bool,
+    /// How many alternative messages to generate and choose from.
+    pub candidates: usize,
+}
+
+/// What `commit` generated and did, printed with `--output json`.
+#[derive(Debug, serde::Serialize)]
+pub struct CommitReport {
+    pub message: String,
+    pub subject: String,
+    pub body: String,
+    pub provider: String,
+    pub model: String,
+    /// Every changed file, including those left out of the prompt.
+    pub files: Vec<String>,
+    pub tokens: TokenUsage,
+    /// The id of the created commit, or `None` on a dry run or when the message was rejected.
+    pub commit: Option<String>,
+}
+
+/// A change in the working tree that isn't staged, as listed before staging.
+#[derive(Debug, Clone, derive_more::Display)]
+enum WorktreeChange {
+    #[display(fmt = "modified:  {}", _0)]
+    Modified(String),
+    #[display(fmt = "deleted:   {}", _0)]
+    Deleted(String),
+    #[display(fmt = "untracked: {}", _0)]
+    Untracked(String),
+}
+
+#[derive(Debug, Clone, Copy, derive_more::Display)]
+enum StageChoice {
+    #[display(fmt = "stage all changes, including untracked files")]
+    All,
+    #[display(fmt = "stage changes to tracked files only")]
+    Tracked,
+    #[display(fmt = "select files to stage")]
+    Files,
+    #[display(fmt = "select files and hunks to stage")]
+    Hunks,
+    #[display(fmt = "abort")]
+    Abort,
+}
+
+/// What to do with a generated message.
+#[derive(Debug, Clone, Copy, derive_more::Display)]
+enum MessageChoice {
+    #[display(fmt = "commit")]
+    Commit,
+    #[display(fmt = "edit in editor")]
+    Edit,
+    #[display(fmt = "regenerate")]
+    Regenerate,
+    #[display(fmt = "regenerate with feedback")]
+    RegenerateWithFeedback,
+    #[display(fmt = "abort")]
+    Abort,
+}
+
+/// One of several generated messages, listed by its subject.
+struct Candidate {
+    number: usize,
+    message: String,
+}
+
+impl std::fmt::Display for Candidate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}. {}",
+            self.number,
+            self.message.trim().lines().next().unwrap_or("")
+        )
+    }
+}
+
+/// A merge, cherry-pick or revert that git stopped in the middle of, which the next commit concludes.
+#[derive(Debug)]
+struct InProgress {
+    state: git2::RepositoryState,
+    /// `MERGE_HEAD`: the commits being merged, which become the other parents.
+    merge_heads: Vec<git2::Oid>,
+    /// `CHERRY_PICK_HEAD`: the commit being picked, whose author is kept.
+    cherry_pick_head: Option<git2::Oid>,
+    /// `MERGE_MSG` without its comment lines.
+    message: Option<String>,
+    /// The files `MERGE_MSG` lists under `# Conflicts:`.
+    conflicts: Vec<String>,
+}
 
 pub struct Committer {
     repository: git2::Repository,
     confirmor: Confirmor,
     comment_generator: CommentGenerator,
+    hooks: Hooks,
+    options: CommitOptions,
 }
 
 impl Committer {
     pub fn new(
+        repository: Repository,
         confirmor: Confirmor,
         comment_generator: CommentGenerator,
+        options: CommitOptions,
     ) -> anyhow::Result<Self> {
-        while let Err(err) = Repository::open(".") {
-            if let Some(parent) = Path::new("..").canonicalize().ok() {
-                std::env::set_current_dir(parent)?;
-            } else {
-                return Err(err.into());
-            }
-        }
-        let repository = Repository::open(".")?;
+        let hooks = Hooks::new(&repository)?;
 
         Ok(Committer {
             repository,
             confirmor,
             comment_generator,
+            hooks,
+            options,
         })
     }
 
-    pub async fn commit(&self) -> anyhow::Result<()> {
-        let diff = self.get_diff()?;
+    pub async fn commit(&self) -> anyhow::Result<CommitReport> {
+        let head = Self::head_commit(&self.repository)?;
+        if self.options.amend && head.is_none() {
+            return Err(crate::UserError::NothingToAmend.into());
+        }
 
-        if !self.diff_has_change(&diff)? {
-            self.stage_all_files()?;
+        let in_progress = self.in_progress()?;
+        // like git, whether or not MERGE_MSG is still there. Rebases stop for amends, so they are allowed.
+        let concluding = matches!(
+            in_progress.state,
+            git2::RepositoryState::Merge
+                | git2::RepositoryState::CherryPick
+                | git2::RepositoryState::CherryPickSequence
+                | git2::RepositoryState::Revert
+                | git2::RepositoryState::RevertSequence
+        );
+        if self.options.amend && concluding {
+            return Err(crate::UserError::AmendInProgress(Self::state_name(in_progress.state).to_string()).into());
         }
+        // a merge commit may bring in nothing new, e.g. with `-s ours`
+        let merging = !in_progress.merge_heads.is_empty();
 
-        let diff = self.get_diff()?;
-        if !self.diff_has_change(&diff)? {
+        let diff = if self.options.dry_run {
+            self.dry_run_diff()?
+        } else {
+            let diff = self.get_diff()?;
+
+            if self.options.all || self.options.include_untracked {
+                self.stage_worktree(self.options.include_untracked)?;
+            } else if !self.options.amend && !merging && !self.diff_has_change(&diff)? {
+                self.stage_changes()?;
+            }
+
+            if self.repository.index()?.has_conflicts() {
+                return Err(crate::UserError::UnresolvedConflicts.into());
+            }
+
+            if !self.options.no_verify {
+                self.hooks.run("pre-commit", &[])?;
+            }
+
+            self.get_diff()?
+        };
+
+        if !self.options.amend && !merging && !self.diff_has_change(&diff)? {
             Err(crate::UserError::NoChangesToCommit.into())
         } else {
-            let diff_str = Self::stringify_diff(&diff)?;
-            let commit_message = self.comment_generator.gen_commit_message(diff_str).await?;
+            let changed_files = diff
+                .deltas()
+                .map(|delta| hunk::delta_path(&delta).to_string_lossy().to_string())
+                .collect::<Vec<_>>();
+            let (files, omitted_files) = Self::prompt_files(&self.repository, &diff)?;
+            let rules = self.lint_rules()?;
+            let mut context = CommitContext {
+                initial_commit: self.base_commit()?.is_none(),
+                previous_message: head
+                    .filter(|_| self.options.amend)
+                    .and_then(|commit| commit.message().map(str::to_string)),
+                omitted_files,
+                merge: merging,
+                prepared_message: in_progress.message.clone(),
+                resolutions: self.resolutions(&in_progress)?,
+                scopes: self.scopes(&diff)?,
+                lint_rules: rules.describe(),
+                ..Default::default()
+            };
+            let prompt_diff = self.comment_generator.prompt_diff(files).await?;
+            let commit_message = self.generate_message(&prompt_diff, &context, &rules).await?;
 
-            self.commit_changes(&commit_message)?;
+            if self.options.dry_run {
+                if let Err(reason) = Self::validate_message(&commit_message, &rules)? {
+                    eprintln!("{}", crate::UserError::InvalidCommitMessage(reason));
+                }
+                return Ok(self.report(&commit_message, changed_files, None));
+            }
+
+            // git passes `merge` whenever the message starts from MERGE_MSG, cherry-picks and reverts included
+            let source: &[&str] = if self.options.amend {
+                &["commit", "HEAD"]
+            } else if in_progress.message.is_some() {
+                &["merge"]
+            } else {
+                &["message"]
+            };
+            let mut commit_message = self.prepare_message(commit_message, source)?;
+
+            let commit = loop {
+                let choice = self.confirmor.select(
+                    "commit with this message?",
+                    vec![
+                        MessageChoice::Commit,
+                        MessageChoice::Edit,
+                        MessageChoice::Regenerate,
+                        MessageChoice::RegenerateWithFeedback,
+                        MessageChoice::Abort,
+                    ],
+                );
+
+                match choice {
+                    MessageChoice::Commit => match Self::validate_message(&commit_message, &rules)? {
+                        Ok(()) => break Some(self.commit_changes(&commit_message, &in_progress)?),
+                        // with -y the same choice would come back forever
+                        Err(reason) if self.confirmor.assume_yes() => {
+                            return Err(crate::UserError::InvalidCommitMessage(reason).into())
+                        }
+                        Err(reason) => eprintln!("{}\n", crate::UserError::InvalidCommitMessage(reason)),
+                    },
+                    MessageChoice::Edit => match editor::edit(&self.repository, &commit_message) {
+                        Ok(edited) if edited.is_empty() => return Err(crate::UserError::EmptyCommitMessage.into()),
+                        Ok(edited) => {
+                            commit_message = edited;
+                            eprintln!("{}\n", commit_message);
+                        }
+                        // keep the generated message, so it can still be used or regenerated
+                        Err(err) => eprintln!("{}\n", err),
+                    },
+                    MessageChoice::Regenerate | MessageChoice::RegenerateWithFeedback => {
+                        context.feedback = match choice {
+                            MessageChoice::RegenerateWithFeedback => {
+                                Some(self.confirmor.input("what should be different?"))
+                                    .filter(|feedback| !feedback.trim().is_empty())
+                            }
+                            _ => None,
+                        };
+                        context.rejected_message = Some(commit_message);
+                        let regenerated = self.generate_message(&prompt_diff, &context, &rules).await?;
+                        commit_message = self.prepare_message(regenerated, source)?;
+                    }
+                    MessageChoice::Abort => break None,
+                }
+            };
+            // commit-msg may have rewritten the message
+            let commit_message = match commit {
+                Some(id) => self.repository.find_commit(id)?.message().unwrap_or("").to_string(),
+                None => commit_message,
+            };
+            Ok(self.report(&commit_message, changed_files, commit))
+        }
+    }
+
+    /// Generates the message, letting the user choose one when `--candidates` asks for several.
+    async fn generate_message(&self, diff: &str, context: &CommitContext, rules: &Rules) -> anyhow::Result<String> {
+        let generated = self
+            .comment_generator
+            .gen_commit_messages(diff, context, self.options.candidates)
+            .await?;
+        let mut candidates = Vec::new();
+        for message in generated {
+            candidates.push(self.retry_invalid(message, diff, context, rules).await?);
+        }
+        if candidates.len() == 1 {
+            return Ok(candidates.remove(0));
+        }
+
+        for (i, message) in candidates.iter().enumerate() {
+            eprintln!("{}. {}\n", i + 1, message.trim().replace('\n', "\n   "));
+        }
+        let candidate = self.confirmor.select(
+            "which message?",
+            candidates
+                .into_iter()
+                .enumerate()
+                .map(|(i, message)| Candidate { number: i + 1, message })
+                .collect(),
+        );
+        self.comment_generator.print(&candidate.message);
+
+        Ok(candidate.message)
+    }
+
+    /// Regenerates `message` with what is wrong with it while it breaks the style or the commit rules,
+    /// at most `lint-retries` times, so the user is shown one that passes.
+    async fn retry_invalid(
+        &self,
+        mut message: String,
+        diff: &str,
+        context: &CommitContext,
+        rules: &Rules,
+    ) -> anyhow::Result<String> {
+        let retries = config::get(config::Item::LintRetries)?
+            .map(|retries| retries.parse())
+            .transpose()?
+            .unwrap_or(commitlint::DEFAULT_RETRIES);
+
+        let mut retried = false;
+        for _ in 0..retries {
+            let Err(reason) = Self::validate_message(&message, rules)? else {
+                break;
+            };
+            eprintln!("the message breaks the commit rules ({}), regenerating it.", reason);
+
+            let violations = format!("It must pass these checks, which it fails: {}.", reason);
+            let context = CommitContext {
+                feedback: Some(match &context.feedback {
+                    Some(feedback) => format!("{} {}", feedback, violations),
+                    None => violations,
+                }),
+                rejected_message: Some(message),
+                ..context.clone()
+            };
+            message = self
+                .comment_generator
+                .gen_commit_message_quietly(diff, &context)
+                .await?;
+            retried = true;
+        }
+
+        for warning in rules.lint(&message).iter().filter(|violation| !violation.error) {
+            eprintln!("warning: {}", warning);
+        }
+        if retried && self.options.candidates <= 1 {
+            self.comment_generator.print(&message);
+        }
+
+        Ok(message)
+    }
+
+    /// Checks the message against the configured style and the errors of the commit rules. The outer error
+    /// is for failing to read the config, the inner one says why the message doesn't conform.
+    fn validate_message(message: &str, rules: &Rules) -> anyhow::Result<Result<(), String>> {
+        let style = match config::get_style()? {
+            config::Style::Plain => Ok(()),
+            config::Style::Conventional => conventional::validate(message),
+            config::Style::Gitmoji => gitmoji::validate(message),
+        };
+
+        let reasons = style
+            .err()
+            .into_iter()
+            .chain(
+                rules
+                    .lint(message)
+                    .iter()
+                    .filter(|violation| violation.error)
+                    .map(ToString::to_string),
+            )
+            .collect::<Vec<_>>();
+
+        Ok(if reasons.is_empty() {
             Ok(())
+        } else {
+            Err(reasons.join("; "))
+        })
+    }
+
+    /// The repository's commitlint rules, or those of the `commitlint` config section.
+    fn lint_rules(&self) -> anyhow::Result<Rules> {
+        Rules::load(self.repository.workdir().unwrap_or(self.repository.path()))
+    }
+
+    /// Conventional Commits scopes of the files in `diff`, mapped by `.hcscopes`.
+    fn scopes(&self, diff: &git2::Diff) -> anyhow::Result<Vec<String>> {
+        let workdir = self.repository.workdir().unwrap_or(self.repository.path());
+        let scope_map = ScopeMap::load(workdir)?;
+        let paths = diff
+            .deltas()
+            .map(|delta| hunk::delta_path(&delta).to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        Ok(scope_map.scopes(paths.iter().map(String::as_str)))
+    }
+
+    /// Runs the prepare-commit-msg hook on a generated message.
+    fn prepare_message(&self, message: String, source: &[&str]) -> anyhow::Result<String> {
+        let prepared_message = self.hooks.run_with_message("prepare-commit-msg", &message, source)?;
+        if prepared_message != message {
+            eprintln!(
+                "prepare-commit-msg hook rewrote the message:\n{}",
+                prepared_message.trim_end()
+            );
         }
+
+        Ok(prepared_message)
     }
 
-    fn get_diff(&self) -> anyhow::Result<git2::Diff<'_>> {
-        let index = self.repository.index()?;
-        let head_commit = self.repository.head()?.peel_to_commit()?;
-        let head_tree = head_commit.tree()?;
+    fn report(&self, message: &str, files: Vec<String>, commit: Option<git2::Oid>) -> CommitReport {
+        let message = message.trim();
+        let (subject, body) = message.split_once('\n').unwrap_or((message, ""));
+
+        CommitReport {
+            message: message.to_string(),
+            subject: subject.trim().to_string(),
+            body: body.trim().to_string(),
+            provider: self.comment_generator.provider().to_string(),
+            model: self.comment_generator.model().to_string(),
+            files,
+            tokens: self.comment_generator.usage(),
+            commit: commit.map(|id| id.to_string()),
+        }
+    }
+
+    /// Writes a generated message in front of the contents of `message_file`, without asking anything.
+    /// Used from the prepare-commit-msg hook, where git has already run pre-commit.
+    pub async fn prepare_message_file(&self, message_file: &Path) -> anyhow::Result<()> {
+        let diff = self.get_diff()?;
+        if !self.diff_has_change(&diff)? {
+            return Ok(());
+        }
+
+        let (files, omitted_files) = Self::prompt_files(&self.repository, &diff)?;
+        let rules = self.lint_rules()?;
+        let context = CommitContext {
+            initial_commit: self.base_commit()?.is_none(),
+            omitted_files,
+            scopes: self.scopes(&diff)?,
+            lint_rules: rules.describe(),
+            ..Default::default()
+        };
+        let prompt_diff = self.comment_generator.prompt_diff(files).await?;
+        let commit_message = self
+            .comment_generator
+            .gen_commit_message(&prompt_diff, &context)
+            .await?;
+        let commit_message = self
+            .retry_invalid(commit_message, &prompt_diff, &context, &rules)
+            .await?;
+
+        let template = std::fs::read_to_string(message_file).unwrap_or_default();
+        std::fs::write(message_file, format!("{}\n{}", commit_message.trim(), template))?;
+
+        Ok(())
+    }
+
+    /// Regenerates the messages of the commits in `range`, which must end at HEAD,
+    /// and rewrites them with their original trees, authors and dates.
+    pub async fn reword(&self, range: &str) -> anyhow::Result<()> {
+        self.ensure_clean_state()?;
+        let commits = self.commits_to_reword(range)?;
+        let rules = self.lint_rules()?;
+
+        let mut messages = Vec::new();
+        for commit in &commits {
+            let old_message = commit.message().unwrap_or("").to_string();
+            let parent_tree = commit.parents().next().map(|parent| parent.tree()).transpose()?;
+            let mut diff = self
+                .repository
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            Self::find_similar(&mut diff)?;
+
+            let (files, omitted_files) = Self::prompt_files(&self.repository, &diff)?;
+            let context = CommitContext {
+                initial_commit: parent_tree.is_none(),
+                previous_message: Some(old_message.clone()),
+                omitted_files,
+                scopes: self.scopes(&diff)?,
+                lint_rules: rules.describe(),
+                ..Default::default()
+            };
+            let prompt_diff = self.comment_generator.prompt_diff(files).await?;
+            let new_message = self
+                .comment_generator
+                .gen_commit_message(&prompt_diff, &context)
+                .await?;
+            let new_message = self.retry_invalid(new_message, &prompt_diff, &context, &rules).await?;
+
+            println!("{}", &commit.id().to_string()[..7]);
+            println!("  old: {}", old_message.trim().replace('\n', "\n       "));
+            println!("  new: {}", new_message.trim().replace('\n', "\n       "));
+            println!();
+
+            if self.confirmor.confirm("use the new message?", true) {
+                let new_message = if self.options.no_verify {
+                    new_message
+                } else {
+                    self.hooks.run_with_message("commit-msg", &new_message, &[])?
+                };
+                messages.push(new_message);
+            } else {
+                messages.push(old_message);
+            }
+        }
+
+        let unchanged = commits
+            .iter()
+            .zip(&messages)
+            .all(|(commit, message)| commit.message() == Some(message.as_str()));
+        if unchanged {
+            return Ok(());
+        }
+
+        let mut parent = commits[0].parents().next();
+        for (commit, message) in commits.iter().zip(&messages) {
+            let parents = parent.iter().collect::<Vec<_>>();
+            let oid = self.create_commit(
+                &commit.author(),
+                &commit.committer(),
+                message,
+                &commit.tree()?,
+                &parents,
+            )?;
+            parent = Some(self.repository.find_commit(oid)?);
+        }
+
+        if let Some(commit) = parent {
+            self.update_head(commit.id(), &format!("huge-commit: reword {}", range))?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolves `range` to the commits to reword, oldest first. A single revision means `<rev>..HEAD`.
+    /// Refuses ranges that don't end at HEAD, contain merges or are already on the upstream branch.
+    fn commits_to_reword(&self, range: &str) -> anyhow::Result<Vec<git2::Commit<'_>>> {
+        let head = Self::head_commit(&self.repository)?.ok_or(crate::UserError::NoCommitsInRange)?;
+
+        let (from, to) = history::resolve_range(&self.repository, range)?;
+        if to.is_some_and(|to| to.id() != head.id()) {
+            return Err(crate::UserError::RangeNotAtHead.into());
+        }
+        let commits = history::walk(&self.repository, head.id(), from.map(|from| from.id()))?;
+
+        if commits.is_empty() {
+            return Err(crate::UserError::NoCommitsInRange.into());
+        }
+        if commits.iter().any(|commit| commit.parent_count() > 1) {
+            return Err(crate::UserError::MergeInRange.into());
+        }
+
+        if let Some(upstream) = self.upstream_commit()? {
+            let published = commits.iter().any(|commit| {
+                upstream.id() == commit.id()
+                    || self
+                        .repository
+                        .graph_descendant_of(upstream.id(), commit.id())
+                        .unwrap_or(false)
+            });
+            if published {
+                return Err(crate::UserError::RangeOnUpstream.into());
+            }
+        }
+
+        Ok(commits)
+    }
+
+    /// Returns the tip of the current branch's upstream, if it has one.
+    fn upstream_commit(&self) -> anyhow::Result<Option<git2::Commit<'_>>> {
+        let head = self.repository.head()?;
+        if !head.is_branch() {
+            return Ok(None);
+        }
+
+        match git2::Branch::wrap(head).upstream() {
+            Ok(upstream) => Ok(Some(upstream.get().peel_to_commit()?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Splits the staged changes into several commits, grouping hunks as the model suggests.
+    pub async fn split(&self) -> anyhow::Result<()> {
+        self.ensure_clean_state()?;
+        if self.options.all || self.options.include_untracked {
+            self.stage_worktree(self.options.include_untracked)?;
+        } else if !self.diff_has_change(&self.get_diff()?)? {
+            self.stage_changes()?;
+        }
+
+        if !self.options.no_verify {
+            self.hooks.run("pre-commit", &[])?;
+        }
+
+        let head = Self::head_commit(&self.repository)?;
+        let head_tree = match &head {
+            Some(head) => head.tree()?,
+            None => self.repository.find_tree(self.repository.treebuilder(None)?.write()?)?,
+        };
 
         let mut opts = DiffOptions::new();
-        let ignore_patterns = Self::read_custom_ignore_patterns(".hcignore")?;
-        for pattern in ignore_patterns {
-            opts.pathspec(pattern);
+        opts.show_binary(true);
+        let mut index = self.repository.index()?;
+        index.read(false)?;
+        let diff = self
+            .repository
+            .diff_tree_to_index(Some(&head_tree), Some(&index), Some(&mut opts))?;
+
+        let files = hunk::collect(&diff)?;
+        if files.is_empty() {
+            return Err(crate::UserError::NoChangesToCommit.into());
+        }
+
+        // files without textual hunks, and those the commit prompt leaves out or describes in one line,
+        // are numbered as a whole so the model can still place them
+        let workdir = self.repository.workdir().unwrap_or(self.repository.path());
+        let matcher = hcignore::matcher(workdir)?;
+        let mut items = Vec::new();
+        let mut split_hunks = Vec::new();
+        for file in &files {
+            let whole_file = if file.hunks.is_empty() {
+                Some("no textual changes".to_string())
+            } else if hcignore::is_ignored(&matcher, &file.path.to_string_lossy()) {
+                Some("left out by .hcignore".to_string())
+            } else {
+                classifier::classify(&self.repository, &file.path, false).map(|kind| format!("{} file", kind))
+            };
+
+            if let Some(description) = whole_file {
+                items.push((file, None));
+                split_hunks.push(SplitHunk {
+                    label: format!("{} ({})", file.path.display(), description),
+                    patch: String::new(),
+                });
+                continue;
+            }
+            for hunk in &file.hunks {
+                items.push((file, Some(hunk)));
+                split_hunks.push(SplitHunk {
+                    label: file.path.display().to_string(),
+                    patch: format!("{}{}", hunk.header, hunk.content),
+                });
+            }
+        }
+
+        let plan = self.comment_generator.gen_split_plan(split_hunks).await?;
+
+        let mut assigned = vec![false; items.len()];
+        let mut groups = plan
+            .into_iter()
+            .map(|planned| {
+                let numbers = planned
+                    .hunks
+                    .into_iter()
+                    .filter(|number| {
+                        (1..=items.len()).contains(number) && !std::mem::replace(&mut assigned[number - 1], true)
+                    })
+                    .collect::<Vec<_>>();
+                (planned.message.trim().to_string(), numbers)
+            })
+            .filter(|(_, numbers)| !numbers.is_empty())
+            .collect::<Vec<_>>();
+
+        // anything the model left out goes into the last commit so the final tree matches the index
+        let leftover = (1..=items.len())
+            .filter(|number| !assigned[number - 1])
+            .collect::<Vec<_>>();
+        match groups.last_mut() {
+            Some((_, numbers)) => numbers.extend(leftover),
+            None => groups.push(("Apply remaining changes".to_string(), leftover)),
+        }
+
+        for (i, (message, numbers)) in groups.iter().enumerate() {
+            println!("{}. {}", i + 1, message);
+            for number in numbers {
+                match items[number - 1] {
+                    (file, Some(hunk)) => println!("     {} {}", file.path.display(), hunk),
+                    (file, None) => println!("     {}", file.path.display()),
+                }
+            }
+        }
+        println!();
+
+        if !self.confirmor.confirm("commit these changes?", true) {
+            return Ok(());
+        }
+
+        if !self.options.no_verify {
+            for (message, _) in groups.iter_mut() {
+                *message = self.hooks.run_with_message("commit-msg", message, &[])?;
+            }
+        }
+
+        let sig = self.repository.signature()?;
+        let mut selection = HunkSelection::default();
+        let mut parent = head;
+        for (message, numbers) in &groups {
+            for number in numbers {
+                match items[number - 1] {
+                    (_, Some(hunk)) => selection.select_hunk(hunk),
+                    (file, None) => selection.select_file(file),
+                }
+            }
+
+            // each commit applies every hunk selected so far to HEAD, as hunk positions are relative to it
+            let mut apply_options = ApplyOptions::new();
+            selection.configure(&mut apply_options);
+            let mut group_index = self
+                .repository
+                .apply_to_tree(&head_tree, &diff, Some(&mut apply_options))?;
+            let tree = self
+                .repository
+                .find_tree(group_index.write_tree_to(&self.repository)?)?;
+
+            let parents = parent.iter().collect::<Vec<_>>();
+            let oid = self.create_commit(&sig, &sig, message, &tree, &parents)?;
+            parent = Some(self.repository.find_commit(oid)?);
+        }
+
+        if let Some(commit) = parent {
+            self.update_head(commit.id(), "huge-commit: split")?;
+            // post-commit can't affect the outcome, so its exit status is ignored like git does
+            let _ = self.hooks.run("post-commit", &[]);
+        }
+
+        Ok(())
+    }
+
+    /// Writes a commit object without moving any reference, signing it when git config or `-S` asks for it.
+    fn create_commit(
+        &self,
+        author: &git2::Signature,
+        committer: &git2::Signature,
+        message: &str,
+        tree: &git2::Tree,
+        parents: &[&git2::Commit],
+    ) -> anyhow::Result<git2::Oid> {
+        let config = self.repository.config()?;
+
+        match Signer::from_config(&config, committer, self.options.gpg_sign.as_ref())? {
+            Some(signer) => {
+                let buffer = self
+                    .repository
+                    .commit_create_buffer(author, committer, message, tree, parents)?;
+                let content = std::str::from_utf8(&buffer)?;
+                let signature = signer.sign(content)?;
+
+                Ok(self.repository.commit_signed(content, &signature, Some("gpgsig"))?)
+            }
+            None => Ok(self
+                .repository
+                .commit(None, author, committer, message, tree, parents)?),
         }
+    }
 
-        let diff =
-            self.repository
-                .diff_tree_to_index(Some(&head_tree), Some(&index), Some(&mut opts))?;
+    /// Points HEAD, or the branch it refers to, at `oid`.
+    fn update_head(&self, oid: git2::Oid, log_message: &str) -> anyhow::Result<()> {
+        let head = self.repository.find_reference("HEAD")?;
+
+        match head.symbolic_target() {
+            Some(target) => {
+                self.repository.reference(target, oid, true, log_message)?;
+            }
+            None => self.repository.set_head_detached(oid)?,
+        }
+
+        Ok(())
+    }
+
+    fn get_diff(&self) -> anyhow::Result<git2::Diff<'_>> {
+        let mut index = self.repository.index()?;
+        index.read(false)?;
+        let base_tree = self.base_commit()?.map(|commit| commit.tree()).transpose()?;
+
+        let mut diff = self
+            .repository
+            .diff_tree_to_index(base_tree.as_ref(), Some(&index), None)?;
+        Self::find_similar(&mut diff)?;
 
         Ok(diff)
     }
 
-    fn read_custom_ignore_patterns(path: &str) -> anyhow::Result<Vec<String>> {
-        let path = std::path::Path::new(path);
-        let file = std::fs::File::open(path);
+    /// The changes a commit would contain, computed without staging anything:
+    /// the staged changes, or the tracked changes in the working tree when nothing is staged or with `-a`.
+    fn dry_run_diff(&self) -> anyhow::Result<git2::Diff<'_>> {
+        let diff = self.get_diff()?;
+        if !(self.options.all || self.options.include_untracked) && self.diff_has_change(&diff)? {
+            return Ok(diff);
+        }
 
-        Ok(file
-            .and_then(|file| {
-                let lines = std::io::BufReader::new(file).lines();
-                let mut patterns = Vec::new();
+        let base_tree = self.base_commit()?.map(|commit| commit.tree()).transpose()?;
+        let mut diff_options = DiffOptions::new();
+        diff_options
+            .include_untracked(self.options.include_untracked)
+            .recurse_untracked_dirs(self.options.include_untracked)
+            .show_untracked_content(self.options.include_untracked);
+        let mut diff = self
+            .repository
+            .diff_tree_to_workdir_with_index(base_tree.as_ref(), Some(&mut diff_options))?;
+        Self::find_similar(&mut diff)?;
 
-                for line in lines {
-                    let line = line?;
-                    if line.trim().is_empty() || line.starts_with('#') {
-                        continue;
-                    }
-                    patterns.push(line);
-                }
+        Ok(diff)
+    }
 
-                Ok(patterns)
-            })
-            .unwrap_or(Vec::new()))
+    /// Detects renames and copies, so moved files show up as `rename from/to` with only the changed lines.
+    pub fn find_similar(diff: &mut git2::Diff) -> anyhow::Result<()> {
+        let threshold = config::get(config::Item::SimilarityThreshold)?
+            .map(|threshold| threshold.parse())
+            .transpose()?
+            .unwrap_or(50);
+        let mut find_options = DiffFindOptions::new();
+        find_options
+            .renames(true)
+            .copies(true)
+            .rename_threshold(threshold)
+            .copy_threshold(threshold);
+        diff.find_similar(Some(&mut find_options))?;
+
+        Ok(())
+    }
+
+    /// Stringifies `diff` for the prompt, leaving out files matched by `.hcignore` and the built-in defaults.
+    /// Returns the remaining files and the paths that were left out.
+    pub fn prompt_files(repository: &Repository, diff: &git2::Diff) -> anyhow::Result<(Vec<FileDiff>, Vec<String>)> {
+        let workdir = repository.workdir().unwrap_or(repository.path());
+        let matcher = hcignore::matcher(workdir)?;
+
+        let (omitted, files): (Vec<_>, Vec<_>) = Self::stringify_diff(repository, diff)?
+            .into_iter()
+            .partition(|file| hcignore::is_ignored(&matcher, &file.path));
+
+        Ok((files, omitted.into_iter().map(|file| file.path).collect()))
+    }
+
+    /// Returns the commit HEAD points to, or `None` if the current branch is unborn.
+    pub fn head_commit(repository: &Repository) -> anyhow::Result<Option<git2::Commit<'_>>> {
+        match repository.head() {
+            Ok(head) => Ok(Some(head.peel_to_commit()?)),
+            Err(err) if matches!(err.code(), git2::ErrorCode::UnbornBranch | git2::ErrorCode::NotFound) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Reads the merge, cherry-pick or revert git stopped in the middle of, if any.
+    fn in_progress(&self) -> anyhow::Result<InProgress> {
+        let state = self.repository.state();
+        let git_dir = self.repository.path();
+
+        let merge_heads = if state == git2::RepositoryState::Merge {
+            std::fs::read_to_string(git_dir.join("MERGE_HEAD"))?
+                .lines()
+                .map(|line| git2::Oid::from_str(line.trim()))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+
+        let cherry_pick_head = match state {
+            git2::RepositoryState::CherryPick | git2::RepositoryState::CherryPickSequence => {
+                std::fs::read_to_string(git_dir.join("CHERRY_PICK_HEAD"))
+                    .ok()
+                    .map(|id| git2::Oid::from_str(id.trim()))
+                    .transpose()?
+            }
+            _ => None,
+        };
+
+        let merge_message = match state {
+            git2::RepositoryState::Merge
+            | git2::RepositoryState::CherryPick
+            | git2::RepositoryState::CherryPickSequence
+            | git2::RepositoryState::Revert
+            | git2::RepositoryState::RevertSequence => std::fs::read_to_string(git_dir.join("MERGE_MSG")).ok(),
+            _ => None,
+        };
+        let merge_message = merge_message.unwrap_or_default();
+
+        let message = merge_message
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let conflicts = merge_message
+            .lines()
+            .skip_while(|line| line.trim() != "# Conflicts:")
+            .skip(1)
+            .map_while(|line| line.strip_prefix("#\t"))
+            .map(|path| path.trim().to_string())
+            .collect();
+
+        Ok(InProgress {
+            state,
+            merge_heads,
+            cherry_pick_head,
+            message: Some(message.trim().to_string()).filter(|message| !message.is_empty()),
+            conflicts,
+        })
+    }
+
+    /// Diffs the files that had conflicts from the merged-in commit to the staged resolution.
+    fn resolutions(&self, in_progress: &InProgress) -> anyhow::Result<Vec<FileDiff>> {
+        let Some(merge_head) = in_progress.merge_heads.first() else {
+            return Ok(Vec::new());
+        };
+        if in_progress.conflicts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tree = self.repository.find_commit(*merge_head)?.tree()?;
+        let mut index = self.repository.index()?;
+        index.read(false)?;
+        let mut diff_options = DiffOptions::new();
+        for path in &in_progress.conflicts {
+            diff_options.pathspec(path);
+        }
+        diff_options.disable_pathspec_match(true);
+        let diff = self
+            .repository
+            .diff_tree_to_index(Some(&tree), Some(&index), Some(&mut diff_options))?;
+
+        Ok(Self::prompt_files(&self.repository, &diff)?.0)
+    }
+
+    /// Removes the state files of the operation the commit concluded, as `git commit` does.
+    /// Sequencer and rebase state is kept so `git cherry-pick --continue` or `git rebase --continue` can go on.
+    fn finish_in_progress(&self, in_progress: &InProgress) -> anyhow::Result<()> {
+        match in_progress.state {
+            git2::RepositoryState::Merge | git2::RepositoryState::CherryPick | git2::RepositoryState::Revert => {
+                self.repository.cleanup_state()?;
+                // written by newer versions of git, which libgit2 doesn't know about
+                self.remove_state_files(&["AUTO_MERGE"])?;
+            }
+            git2::RepositoryState::CherryPickSequence | git2::RepositoryState::RevertSequence => {
+                self.remove_state_files(&["CHERRY_PICK_HEAD", "REVERT_HEAD", "MERGE_MSG", "AUTO_MERGE"])?;
+            }
+            git2::RepositoryState::Clean => {}
+            state => eprintln!(
+                "committed in the middle of a {}. continue it with git.",
+                Self::state_name(state)
+            ),
+        }
+
+        Ok(())
+    }
+
+    fn remove_state_files(&self, files: &[&str]) -> anyhow::Result<()> {
+        for file in files {
+            let path = self.repository.path().join(file);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refuses commands that don't conclude a merge, cherry-pick or revert while one is in progress,
+    /// as their commits would leave its state behind for the next `git commit`.
+    fn ensure_clean_state(&self) -> anyhow::Result<()> {
+        match self.repository.state() {
+            git2::RepositoryState::Clean => Ok(()),
+            state => Err(crate::UserError::OperationInProgress(Self::state_name(state).to_string()).into()),
+        }
+    }
+
+    fn state_name(state: git2::RepositoryState) -> &'static str {
+        match state {
+            git2::RepositoryState::Merge => "merge",
+            git2::RepositoryState::Revert | git2::RepositoryState::RevertSequence => "revert",
+            git2::RepositoryState::CherryPick | git2::RepositoryState::CherryPickSequence => "cherry-pick",
+            git2::RepositoryState::Bisect => "bisect",
+            git2::RepositoryState::ApplyMailbox | git2::RepositoryState::ApplyMailboxOrRebase => "am",
+            _ => "rebase",
+        }
+    }
+
+    /// Returns the commit the staged changes are compared against.
+    /// When amending, this is HEAD's first parent so the diff covers both HEAD and the newly staged changes.
+    fn base_commit(&self) -> anyhow::Result<Option<git2::Commit<'_>>> {
+        let head = Self::head_commit(&self.repository)?;
+
+        if self.options.amend {
+            Ok(head.and_then(|commit| commit.parent(0).ok()))
+        } else {
+            Ok(head)
+        }
     }
 
     fn diff_has_change(&self, diff: &git2::Diff) -> anyhow::Result<bool> {
         Ok(0 < diff.stats()?.files_changed())
     }
 
-    fn stage_all_files(&self) -> anyhow::Result<()> {
+    fn stage_changes(&self) -> anyhow::Result<()> {
+        let changes = self.worktree_changes()?;
+        if changes.is_empty() {
+            return Err(crate::UserError::NoChangesToCommit.into());
+        }
+
+        eprintln!("No changes to commit. changes in the working tree:");
+        for change in &changes {
+            eprintln!("  {}", change);
+        }
+        eprintln!();
+
+        let choice = self.confirmor.select(
+            "what do you want to stage?",
+            vec![
+                StageChoice::All,
+                StageChoice::Tracked,
+                StageChoice::Files,
+                StageChoice::Hunks,
+                StageChoice::Abort,
+            ],
+        );
+
+        match choice {
+            StageChoice::All => self.stage_worktree(true),
+            StageChoice::Tracked => self.stage_worktree(false),
+            StageChoice::Files => {
+                let changes = self.confirmor.multi_select("select files to stage", changes, false);
+                self.stage_worktree_changes(&changes)
+            }
+            StageChoice::Hunks => self.stage_selected_hunks(),
+            StageChoice::Abort => Err(crate::UserError::NoChangesToCommit.into()),
+        }
+    }
+
+    /// Lists unstaged changes. Nested repositories are left out, as staging them would embed them as gitlinks.
+    fn worktree_changes(&self) -> anyhow::Result<Vec<WorktreeChange>> {
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .exclude_submodules(true);
+        let statuses = self.repository.statuses(Some(&mut opts))?;
+
+        let changes = statuses
+            .iter()
+            .filter_map(|entry| {
+                let path = entry.path()?.to_string();
+                let status = entry.status();
+
+                if status.is_wt_new() {
+                    // untracked directories are only reported as a whole when they are repositories themselves
+                    (!path.ends_with('/')).then_some(WorktreeChange::Untracked(path))
+                } else if status.is_wt_deleted() {
+                    Some(WorktreeChange::Deleted(path))
+                } else if status.is_wt_modified() || status.is_wt_typechange() || status.is_wt_renamed() {
+                    Some(WorktreeChange::Modified(path))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Ok(changes)
+    }
+
+    /// Stages every change to tracked files like `git add -u`, and untracked files too when `include_untracked` is set.
+    fn stage_worktree(&self, include_untracked: bool) -> anyhow::Result<()> {
+        let changes = self
+            .worktree_changes()?
+            .into_iter()
+            .filter(|change| include_untracked || !matches!(change, WorktreeChange::Untracked(_)))
+            .collect::<Vec<_>>();
+
+        self.stage_worktree_changes(&changes)
+    }
+
+    fn stage_worktree_changes(&self, changes: &[WorktreeChange]) -> anyhow::Result<()> {
         let mut index = self.repository.index()?;
 
-        let stage = self
-            .confirmor
-            .confirm("No changes to commit. stage all changes?", true);
+        for change in changes {
+            match change {
+                WorktreeChange::Modified(path) | WorktreeChange::Untracked(path) => index.add_path(Path::new(path))?,
+                WorktreeChange::Deleted(path) => index.remove_path(Path::new(path))?,
+            }
+        }
+        index.write()?;
 
-        println!();
+        Ok(())
+    }
 
-        if stage {
-            index
-                .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
-                .expect("Failed to stage changes");
-            index.write().expect("Failed to write index");
-            Ok(())
-        } else {
-            Err(crate::UserError::NoChangesToCommit.into())
+    /// Lets the user pick files, then hunks within each file, like `git add -p`, and stages only those.
+    fn stage_selected_hunks(&self) -> anyhow::Result<()> {
+        let mut opts = DiffOptions::new();
+        // binary files can only be applied to the index with their contents in the patch
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true)
+            .show_binary(true);
+        let diff = self.repository.diff_index_to_workdir(None, Some(&mut opts))?;
+
+        let files = hunk::collect(&diff)?;
+        if files.is_empty() {
+            return Err(crate::UserError::NoChangesToCommit.into());
         }
+
+        let files = self.confirmor.multi_select("select files to stage", files, false);
+
+        // untracked files can't be patched into the index, so they are added as a whole
+        let (untracked, files): (Vec<_>, Vec<_>) = files
+            .into_iter()
+            .partition(|file| file.status == git2::Delta::Untracked);
+        if !untracked.is_empty() {
+            let mut index = self.repository.index()?;
+            for file in &untracked {
+                index.add_path(&file.path)?;
+            }
+            index.write()?;
+        }
+
+        let mut selection = HunkSelection::default();
+        for file in &files {
+            if file.hunks.len() <= 1 {
+                selection.select_file(file);
+                continue;
+            }
+
+            eprintln!("{}", file.path.display());
+            for hunk in &file.hunks {
+                eprint!("{}{}", hunk.header, hunk.content);
+            }
+            eprintln!();
+
+            let message = format!("select hunks of {} to stage", file.path.display());
+            let hunks = self.confirmor.multi_select(&message, file.hunks.iter().collect(), true);
+            hunks.into_iter().for_each(|hunk| selection.select_hunk(hunk));
+        }
+
+        if selection.is_empty() {
+            return if untracked.is_empty() {
+                Err(crate::UserError::NoChangesToCommit.into())
+            } else {
+                Ok(())
+            };
+        }
+
+        let mut apply_options = ApplyOptions::new();
+        selection.configure(&mut apply_options);
+        self.repository
+            .apply(&diff, ApplyLocation::Index, Some(&mut apply_options))?;
+
+        Ok(())
     }
 
-    fn commit_changes(&self, commit_message: &str) -> anyhow::Result<()> {
+    fn commit_changes(&self, commit_message: &str, in_progress: &InProgress) -> anyhow::Result<git2::Oid> {
         let mut index = self.repository.index()?;
+        index.read(false)?;
+
+        let commit_message = if self.options.no_verify {
+            commit_message.to_string()
+        } else {
+            self.hooks.run_with_message("commit-msg", commit_message, &[])?
+        };
+        let commit_message = commit_message.as_str();
 
-        let commit = self.confirmor.confirm("commit with this message?", true);
+        let sig = self.repository.signature()?;
+        let tree_id = index.write_tree()?;
+        let tree = self.repository.find_tree(tree_id)?;
+        let head = Self::head_commit(&self.repository)?;
+        let subject = commit_message.lines().next().unwrap_or("");
 
-        if commit {
-            let sig = self.repository.signature()?;
-            let tree_id = index.write_tree()?;
-            let tree = self.repository.find_tree(tree_id)?;
-            let head = self.repository.head()?.peel_to_commit()?;
-            self.repository
-                .commit(Some("HEAD"), &sig, &sig, commit_message, &tree, &[&head])?;
+        let oid = match head {
+            Some(head) if self.options.amend => {
+                let parents = head.parents().collect::<Vec<_>>();
+                let parents = parents.iter().collect::<Vec<_>>();
+                let oid = self.create_commit(&head.author(), &sig, commit_message, &tree, &parents)?;
+                self.update_head(oid, &format!("commit (amend): {}", subject))?;
+                oid
+            }
+            head => {
+                let merge_heads = in_progress
+                    .merge_heads
+                    .iter()
+                    .map(|id| self.repository.find_commit(*id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let parents = head.iter().chain(&merge_heads).collect::<Vec<_>>();
+                // like git, a picked commit keeps its author
+                let picked = in_progress
+                    .cherry_pick_head
+                    .map(|id| self.repository.find_commit(id))
+                    .transpose()?;
+                let author = picked.as_ref().map(|commit| commit.author()).unwrap_or(sig.clone());
+                let oid = self.create_commit(&author, &sig, commit_message, &tree, &parents)?;
+                let log_message = if parents.is_empty() {
+                    format!("commit (initial): {}", subject)
+                } else if !merge_heads.is_empty() {
+                    format!("commit (merge): {}", subject)
+                } else if picked.is_some() {
+                    format!("commit (cherry-pick): {}", subject)
+                } else {
+                    format!("commit: {}", subject)
+                };
+                self.update_head(oid, &log_message)?;
+                oid
+            }
         };
 
-        Ok(())
+        self.finish_in_progress(in_progress)?;
+
+        // post-commit can't affect the outcome, so its exit status is ignored like git does
+        let _ = self.hooks.run("post-commit", &[]);
+
+        Ok(oid)
     }
 
-    fn stringify_diff(diff: &git2::Diff) -> anyhow::Result<String> {
-        let mut diff_buf = String::new();
+    /// Stringifies `diff` per file. Binary, generated, vendored and snapshot files are described in one line
+    /// instead of their patch, so the prompt is spent on code. Submodule updates list the commits they bring in.
+    fn stringify_diff(repository: &Repository, diff: &git2::Diff) -> anyhow::Result<Vec<FileDiff>> {
+        struct Entry {
+            file: FileDiff,
+            old_path: String,
+            kind: Option<FileKind>,
+            submodule: bool,
+            status: git2::Delta,
+            old_id: git2::Oid,
+            new_id: git2::Oid,
+        }
+
+        let mut entries: Vec<Entry> = Vec::new();
 
         let _ = &diff
-            .print(DiffFormat::Patch, |_delta, _hunk, line| {
+            .print(DiffFormat::Patch, |delta, _hunk, line| {
+                let path = hunk::delta_path(&delta);
+                let path_str = path.to_string_lossy().to_string();
+                if entries.last().map(|entry| entry.file.path != path_str).unwrap_or(true) {
+                    entries.push(Entry {
+                        file: FileDiff {
+                            path: path_str,
+                            patch: String::new(),
+                        },
+                        old_path: delta
+                            .old_file()
+                            .path()
+                            .map(|path| path.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                        kind: classifier::classify(repository, &path, delta.flags().is_binary()),
+                        submodule: delta.old_file().mode() == git2::FileMode::Commit
+                            || delta.new_file().mode() == git2::FileMode::Commit,
+                        status: delta.status(),
+                        old_id: delta.old_file().id(),
+                        new_id: delta.new_file().id(),
+                    });
+                }
+                let entry = entries.last_mut().expect("entry was just pushed");
+
+                if line.origin_value() == git2::DiffLineType::Binary {
+                    entry.kind = Some(FileKind::Binary);
+                }
+                if entry.kind.is_some() || entry.submodule {
+                    return true;
+                }
+
                 let mut buf = String::new();
 
-                line.content()
-                    .read_to_string(&mut buf)
-                    .expect("Failed to read line");
+                line.content().read_to_string(&mut buf).expect("Failed to read line");
 
-                diff_buf
+                entry
+                    .file
+                    .patch
                     .write_fmt(format_args!("{} {}", line.origin(), buf))
                     .expect("Failed to write diff");
 
@@ -154,6 +1282,37 @@ impl Committer {
             })
             .expect("Failed to print diff");
 
-        Ok(diff_buf)
+        let blob_size = |id: git2::Oid| {
+            if id.is_zero() {
+                0
+            } else {
+                repository.find_blob(id).map(|blob| blob.size()).unwrap_or(0)
+            }
+        };
+
+        Ok(entries
+            .into_iter()
+            .map(|mut entry| {
+                if entry.submodule {
+                    entry.file.patch = submodule::describe(repository, &entry.file.path, entry.old_id, entry.new_id);
+                } else if let Some(kind) = entry.kind {
+                    let path = match entry.status {
+                        git2::Delta::Renamed | git2::Delta::Copied => {
+                            format!("{} -> {}", entry.old_path, entry.file.path)
+                        }
+                        _ => entry.file.path.clone(),
+                    };
+                    entry.file.patch = classifier::describe(
+                        &path,
+                        kind,
+                        entry.status,
+                        blob_size(entry.old_id),
+                        blob_size(entry.new_id),
+                    );
+                }
+
+                entry.file
+            })
+            .collect())
     }
 }
@@ -1,5 +1,6 @@
+use crate::commitlint;
 use clap::Subcommand;
-use std::{any::Any, borrow::Borrow, fs::File, io::Write};
+use std::{borrow::Borrow, fs::File, io::Write};
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
@@ -26,11 +27,14 @@ pub enum Item {
     ConfigPath,
     AnthropicApiKey,
     AnthropicModel,
-}
-
-pub struct Config {
-    key: Item,
-    value: Option<String>,
+    TokenBudget,
+    SimilarityThreshold,
+    MessageShape,
+    SubjectLength,
+    Style,
+    GitmojiFormat,
+    LintRetries,
+    CommitlintJs,
 }
 
 pub fn get<K: Borrow<Item>>(key: K) -> anyhow::Result<Option<String>> {
@@ -42,6 +46,14 @@ pub fn get<K: Borrow<Item>>(key: K) -> anyhow::Result<Option<String>> {
         Item::AnthropicApiKey => config.anthropic_api_key,
         Item::OpenaiModel => config.openai_model,
         Item::AnthropicModel => config.anthropic_model,
+        Item::TokenBudget => config.token_budget.map(|budget| budget.to_string()),
+        Item::SimilarityThreshold => config.similarity_threshold.map(|threshold| threshold.to_string()),
+        Item::MessageShape => config.message_shape.map(|shape| shape.to_string()),
+        Item::SubjectLength => config.subject_length.map(|length| length.to_string()),
+        Item::Style => config.style.map(|style| style.to_string()),
+        Item::GitmojiFormat => config.gitmoji_format.map(|format| format.to_string()),
+        Item::LintRetries => config.lint_retries.map(|retries| retries.to_string()),
+        Item::CommitlintJs => config.commitlint_js.map(|allowed| allowed.to_string()),
         Item::ConfigPath => Some(config_path()?.to_string_lossy().to_string()),
     };
 
@@ -54,15 +66,65 @@ pub fn get_model_provider() -> anyhow::Result<Option<ModelProvider>> {
     Ok(config.model_provider)
 }
 
+pub fn get_message_shape() -> anyhow::Result<MessageShape> {
+    let config = read_config()?;
+
+    Ok(config.message_shape.unwrap_or_default())
+}
+
+pub fn get_style() -> anyhow::Result<Style> {
+    let config = read_config()?;
+
+    Ok(config.style.unwrap_or_default())
+}
+
+pub fn get_gitmoji_format() -> anyhow::Result<GitmojiFormat> {
+    let config = read_config()?;
+
+    Ok(config.gitmoji_format.unwrap_or_default())
+}
+
+/// Whether a repository's JavaScript commitlint configuration may be run with `node`.
+pub fn get_commitlint_js() -> anyhow::Result<bool> {
+    let config = read_config()?;
+
+    Ok(config.commitlint_js.unwrap_or(false))
+}
+
+/// The `[commitlint]` section, used in repositories without a commitlint configuration of their own.
+pub fn get_commitlint() -> anyhow::Result<Option<commitlint::Config>> {
+    let config = read_config()?;
+
+    Ok(config.commitlint)
+}
+
 pub fn set<K: Borrow<Item>>(key: K, value: Option<String>) -> anyhow::Result<()> {
     let mut config = read_config()?;
 
     match key.borrow() {
-        Item::ModelProvider => config.model_provider = value.and_then(|v| Some(ModelProvider::try_from(v).unwrap())),
+        Item::ModelProvider => config.model_provider = value.map(|v| ModelProvider::try_from(v).unwrap()),
         Item::OpenaiApiKey => config.openai_api_key = value,
         Item::AnthropicApiKey => config.anthropic_api_key = value,
         Item::OpenaiModel => config.openai_model = value,
         Item::AnthropicModel => config.anthropic_model = value,
+        Item::TokenBudget => config.token_budget = value.map(|v| v.parse()).transpose()?,
+        Item::SimilarityThreshold => config.similarity_threshold = value.map(|v| v.parse()).transpose()?,
+        Item::MessageShape => {
+            config.message_shape = value
+                .map(MessageShape::try_from)
+                .transpose()
+                .map_err(anyhow::Error::msg)?
+        }
+        Item::SubjectLength => config.subject_length = value.map(|v| v.parse()).transpose()?,
+        Item::Style => config.style = value.map(Style::try_from).transpose().map_err(anyhow::Error::msg)?,
+        Item::GitmojiFormat => {
+            config.gitmoji_format = value
+                .map(GitmojiFormat::try_from)
+                .transpose()
+                .map_err(anyhow::Error::msg)?
+        }
+        Item::LintRetries => config.lint_retries = value.map(|v| v.parse()).transpose()?,
+        Item::CommitlintJs => config.commitlint_js = value.map(|v| v.parse()).transpose()?,
         Item::ConfigPath => unimplemented!("Setting config path is currently not supported."),
     };
 
@@ -117,6 +179,81 @@ impl TryFrom<String> for ModelProvider {
     }
 }
 
+/// Whether commit messages are a single subject line or a subject followed by a body.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize, derive_more::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageShape {
+    #[default]
+    #[display(fmt = "subject")]
+    Subject,
+    #[display(fmt = "subject-and-body")]
+    SubjectAndBody,
+}
+
+impl TryFrom<String> for MessageShape {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<MessageShape, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "subject" => Ok(MessageShape::Subject),
+            "subject-and-body" => Ok(MessageShape::SubjectAndBody),
+            _ => Err("Invalid message shape. Must be either 'subject' or 'subject-and-body'"),
+        }
+    }
+}
+
+/// The convention the first line of commit messages follows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize, derive_more::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum Style {
+    /// A plain sentence starting with a verb.
+    #[default]
+    #[display(fmt = "plain")]
+    Plain,
+    /// `type(scope): description`, as in conventionalcommits.org.
+    #[display(fmt = "conventional")]
+    Conventional,
+    /// A gitmoji followed by a plain sentence, as in gitmoji.dev.
+    #[display(fmt = "gitmoji")]
+    Gitmoji,
+}
+
+impl TryFrom<String> for Style {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Style, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(Style::Plain),
+            "conventional" => Ok(Style::Conventional),
+            "gitmoji" => Ok(Style::Gitmoji),
+            _ => Err("Invalid style. Must be one of 'plain', 'conventional' or 'gitmoji'"),
+        }
+    }
+}
+
+/// How the gitmoji style writes the gitmoji: as the emoji itself or as its `:shortcode:`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize, derive_more::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitmojiFormat {
+    #[default]
+    #[display(fmt = "emoji")]
+    Emoji,
+    #[display(fmt = "shortcode")]
+    Shortcode,
+}
+
+impl TryFrom<String> for GitmojiFormat {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<GitmojiFormat, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "emoji" => Ok(GitmojiFormat::Emoji),
+            "shortcode" => Ok(GitmojiFormat::Shortcode),
+            _ => Err("Invalid gitmoji format. Must be either 'emoji' or 'shortcode'"),
+        }
+    }
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct ConfigStore {
     pub model_provider: Option<ModelProvider>,
@@ -124,4 +261,22 @@ pub struct ConfigStore {
     pub openai_model: Option<String>,
     pub anthropic_api_key: Option<String>,
     pub anthropic_model: Option<String>,
+    /// Tokens of diff sent to the model before large files get summarized. Defaults per model.
+    pub token_budget: Option<usize>,
+    /// Similarity percentage for a deleted and an added file to be shown as a rename or copy. Defaults to 50 like git.
+    pub similarity_threshold: Option<u16>,
+    /// Defaults to a single subject line.
+    pub message_shape: Option<MessageShape>,
+    /// Characters the subject line may have before a warning is shown. Defaults to 72.
+    pub subject_length: Option<usize>,
+    /// Defaults to plain sentences.
+    pub style: Option<Style>,
+    /// Defaults to the emoji itself.
+    pub gitmoji_format: Option<GitmojiFormat>,
+    /// Times a message that breaks the commit rules is regenerated before it is shown. Defaults to 2.
+    pub lint_retries: Option<usize>,
+    /// Runs `commitlint.config.js` and the like, which is code from the repository. Defaults to false.
+    pub commitlint_js: Option<bool>,
+    /// Edited by hand, as `config set` only sets single values.
+    pub commitlint: Option<commitlint::Config>,
 }
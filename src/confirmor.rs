@@ -7,6 +7,10 @@ impl Confirmor {
         Ok(Confirmor { assume_yes })
     }
 
+    pub fn assume_yes(&self) -> bool {
+        self.assume_yes
+    }
+
     pub fn confirm(&self, message: &'static str, default: bool) -> bool {
         if self.assume_yes {
             true
@@ -15,9 +19,53 @@ impl Confirmor {
                 .with_default(default)
                 .prompt()
                 .expect("Failed to get user input");
-            println!();
+            eprintln!();
 
             confirm
         }
     }
+
+    /// Asks the user to pick one of `options`. The first option is taken when assuming yes.
+    pub fn select<T: std::fmt::Display>(&self, message: &str, mut options: Vec<T>) -> T {
+        if self.assume_yes {
+            options.remove(0)
+        } else {
+            let selected = inquire::Select::new(message, options)
+                .prompt()
+                .expect("Failed to get user input");
+            eprintln!();
+
+            selected
+        }
+    }
+
+    /// Asks the user for a line of text. An empty answer is returned when assuming yes.
+    pub fn input(&self, message: &str) -> String {
+        if self.assume_yes {
+            String::new()
+        } else {
+            let input = inquire::Text::new(message).prompt().expect("Failed to get user input");
+            eprintln!();
+
+            input
+        }
+    }
+
+    /// Asks the user to pick any of `options`. Every option is taken when assuming yes.
+    pub fn multi_select<T: std::fmt::Display>(&self, message: &str, options: Vec<T>, all_selected: bool) -> Vec<T> {
+        if self.assume_yes {
+            options
+        } else {
+            let prompt = inquire::MultiSelect::new(message, options);
+            let prompt = if all_selected {
+                prompt.with_all_selected_by_default()
+            } else {
+                prompt
+            };
+            let selected = prompt.prompt().expect("Failed to get user input");
+            eprintln!();
+
+            selected
+        }
+    }
 }
@@ -0,0 +1,279 @@
+use std::path::Path;
+
+/// The types of `@commitlint/config-conventional`, which semantic-release understands.
+pub const TYPES: &[&str] = &[
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+const BREAKING_CHANGE_TOKENS: &[&str] = &["BREAKING CHANGE:", "BREAKING-CHANGE:"];
+
+/// The `type(scope)!: description` first line of a Conventional Commit.
+#[derive(Debug)]
+pub struct Header<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+impl<'a> Header<'a> {
+    pub fn parse(line: &'a str) -> Result<Self, String> {
+        let (prefix, description) = line
+            .split_once(": ")
+            .ok_or("the first line must start with `type: ` or `type(scope): `")?;
+
+        let (prefix, breaking) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => {
+                let scope = scope
+                    .strip_suffix(')')
+                    .filter(|scope| !scope.trim().is_empty() && !scope.contains(['(', ')']))
+                    .ok_or(format!("`{}` is not a valid scope", prefix))?;
+                (kind, Some(scope))
+            }
+            None => (prefix, None),
+        };
+
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(format!("`{}` is not a valid type", kind));
+        }
+        if description.trim().is_empty() || description.starts_with(char::is_whitespace) {
+            return Err("the description after the colon is empty".to_string());
+        }
+
+        Ok(Header {
+            kind,
+            scope,
+            breaking,
+            description,
+        })
+    }
+}
+
+impl std::fmt::Display for Header<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(scope) = self.scope {
+            write!(f, "({})", scope)?;
+        }
+        if self.breaking {
+            write!(f, "!")?;
+        }
+        write!(f, ": {}", self.description)
+    }
+}
+
+/// Checks `message` against the Conventional Commits specification, returning why it doesn't conform.
+pub fn validate(message: &str) -> Result<(), String> {
+    let mut lines = message.trim().lines();
+    Header::parse(lines.next().unwrap_or(""))?;
+
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        return Err("the first line must be followed by a blank line".to_string());
+    }
+
+    for line in lines {
+        let is_breaking_change = line
+            .get(..BREAKING_CHANGE_TOKENS[0].len())
+            .is_some_and(|token| BREAKING_CHANGE_TOKENS.iter().any(|t| t.eq_ignore_ascii_case(token)));
+        if is_breaking_change && !BREAKING_CHANGE_TOKENS.iter().any(|token| line.starts_with(token)) {
+            return Err("`BREAKING CHANGE:` must be written in uppercase".to_string());
+        }
+        if is_breaking_change && line[BREAKING_CHANGE_TOKENS[0].len()..].trim().is_empty() {
+            return Err("`BREAKING CHANGE:` must describe what breaks".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the scope of the first line with `scope`, leaving messages that don't parse untouched.
+pub fn set_scope(message: &str, scope: &str) -> String {
+    let (first_line, rest) = message.split_once('\n').unwrap_or((message, ""));
+
+    match Header::parse(first_line) {
+        Ok(header) => {
+            let header = Header {
+                scope: Some(scope),
+                ..header
+            };
+            if rest.is_empty() {
+                header.to_string()
+            } else {
+                format!("{}\n{}", header, rest)
+            }
+        }
+        Err(_) => message.to_string(),
+    }
+}
+
+/// The `BREAKING CHANGE:` footer of `message` and everything after it.
+pub fn breaking_change_footer(message: &str) -> Option<String> {
+    let lines = message.lines().collect::<Vec<_>>();
+    let start = lines
+        .iter()
+        .position(|line| BREAKING_CHANGE_TOKENS.iter().any(|token| line.starts_with(token)))?;
+
+    let footer = lines[start..]
+        .iter()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .copied()
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(footer.trim().to_string())
+}
+
+/// Maps paths to scopes for monorepos, read from `.hcscopes` at the root of the working tree.
+/// Each line is `<path> <scope>`, and the longest matching path wins. `#` starts a comment.
+#[derive(Debug, Default)]
+pub struct ScopeMap {
+    entries: Vec<(String, String)>,
+}
+
+impl ScopeMap {
+    pub fn load(workdir: &Path) -> anyhow::Result<Self> {
+        let path = workdir.join(".hcscopes");
+        if !path.exists() {
+            return Ok(ScopeMap::default());
+        }
+
+        let mut entries = std::fs::read_to_string(path)?
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter_map(|line| line.split_once(char::is_whitespace))
+            .map(|(path, scope)| (path.trim_end_matches('/').to_string(), scope.trim().to_string()))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+
+        Ok(ScopeMap { entries })
+    }
+
+    /// The distinct scopes of `paths`, sorted. Paths outside of every mapped directory have no scope.
+    pub fn scopes<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut scopes = paths
+            .into_iter()
+            .filter_map(|path| {
+                self.entries
+                    .iter()
+                    .find(|(prefix, _)| path == prefix || path.starts_with(&format!("{}/", prefix)))
+                    .map(|(_, scope)| scope.clone())
+            })
+            .collect::<Vec<_>>();
+        scopes.sort();
+        scopes.dedup();
+
+        scopes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_header() {
+        let header = Header::parse("feat(api)!: add a flag").unwrap();
+
+        assert_eq!(header.kind, "feat");
+        assert_eq!(header.scope, Some("api"));
+        assert!(header.breaking);
+        assert_eq!(header.description, "add a flag");
+        assert_eq!(header.to_string(), "feat(api)!: add a flag");
+    }
+
+    #[test]
+    fn parse_header_without_scope() {
+        let header = Header::parse("fix: handle empty input").unwrap();
+
+        assert_eq!(header.kind, "fix");
+        assert_eq!(header.scope, None);
+        assert!(!header.breaking);
+        assert_eq!(header.to_string(), "fix: handle empty input");
+    }
+
+    #[test]
+    fn parse_header_rejects_malformed_lines() {
+        assert!(Header::parse("add a flag").is_err());
+        assert!(Header::parse("feat:add a flag").is_err());
+        assert!(Header::parse("feat: ").is_err());
+        assert!(Header::parse("feat:  add a flag").is_err());
+        assert!(Header::parse("feat(): add a flag").is_err());
+        assert!(Header::parse("feat(api: add a flag").is_err());
+        assert!(Header::parse("feat(a(b)): add a flag").is_err());
+        assert!(Header::parse("new feature: add a flag").is_err());
+        assert!(Header::parse(": add a flag").is_err());
+    }
+
+    #[test]
+    fn validate_accepts_conventional_messages() {
+        assert_eq!(validate("fix: handle empty input"), Ok(()));
+        assert_eq!(validate("fix: handle empty input\n\nThe parser panicked."), Ok(()));
+        assert_eq!(
+            validate("feat!: drop v1\n\nBREAKING CHANGE: v1 clients stop working"),
+            Ok(())
+        );
+        assert_eq!(
+            validate("feat!: drop v1\n\nBREAKING-CHANGE: v1 clients stop working"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn validate_requires_blank_line_after_header() {
+        assert!(validate("fix: handle empty input\nThe parser panicked.").is_err());
+    }
+
+    #[test]
+    fn validate_checks_breaking_change_footer() {
+        assert!(validate("feat!: drop v1\n\nbreaking change: v1 is gone").is_err());
+        assert!(validate("feat!: drop v1\n\nBREAKING CHANGE: ").is_err());
+    }
+
+    #[test]
+    fn set_scope_replaces_scope() {
+        assert_eq!(
+            set_scope("feat(api): add a flag\n\nbody", "cli"),
+            "feat(cli): add a flag\n\nbody"
+        );
+        assert_eq!(set_scope("feat!: add a flag", "cli"), "feat(cli)!: add a flag");
+        assert_eq!(set_scope("Add a flag", "cli"), "Add a flag");
+    }
+
+    #[test]
+    fn breaking_change_footer_takes_the_rest_of_the_message() {
+        let message = "feat!: drop v1\n\nbody\n\nBREAKING CHANGE: v1 is gone\n```\nuse v2\n```\nRefs #3";
+
+        assert_eq!(
+            breaking_change_footer(message).as_deref(),
+            Some("BREAKING CHANGE: v1 is gone\nuse v2\nRefs #3")
+        );
+        assert_eq!(breaking_change_footer("fix: x\n\nbody"), None);
+    }
+
+    #[test]
+    fn scopes_use_the_longest_matching_path() {
+        let mut entries = vec![
+            ("packages".to_string(), "packages".to_string()),
+            ("packages/api".to_string(), "api".to_string()),
+            ("packages/cli".to_string(), "cli".to_string()),
+        ];
+        entries.sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+        let scopes = ScopeMap { entries };
+
+        assert_eq!(
+            scopes.scopes([
+                "packages/api/src/lib.rs",
+                "packages/cli/main.rs",
+                "packages/api/Cargo.toml"
+            ]),
+            vec!["api", "cli"]
+        );
+        assert_eq!(scopes.scopes(["packages/web/index.ts"]), vec!["packages"]);
+        assert_eq!(scopes.scopes(["packages-old/x", "README.md"]), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+use std::process::Command;
+
+/// What git falls back to when no editor is configured. Windows has no `vi` outside of Git for Windows.
+#[cfg(unix)]
+const DEFAULT_EDITOR: &str = "vi";
+#[cfg(not(unix))]
+const DEFAULT_EDITOR: &str = "notepad";
+
+const INSTRUCTIONS: &str = "
+# Edit the commit message. Lines starting with '#' will be ignored,
+# and an empty message aborts the commit.
+";
+
+/// Opens `message` in the user's editor, the way `git commit` does, and returns the edited text
+/// without comment lines.
+pub fn edit(repository: &git2::Repository, message: &str) -> anyhow::Result<String> {
+    let path = repository.path().join("COMMIT_EDITMSG");
+    std::fs::write(&path, format!("{}\n{}", message.trim_end(), INSTRUCTIONS))?;
+
+    let editor = editor(repository);
+    let status = command(&editor, &path)
+        .status()
+        .map_err(|err| crate::UserError::EditorNotStarted(editor.clone(), err.to_string()))?;
+    if !status.success() {
+        return Err(crate::UserError::EditorFailed(editor).into());
+    }
+
+    let edited = std::fs::read_to_string(&path)?
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(edited.trim().to_string())
+}
+
+/// The editor may come with arguments, so it is run through the shell like git does.
+#[cfg(unix)]
+fn command(editor: &str, path: &Path) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(editor)
+        .arg(path);
+
+    command
+}
+
+/// Windows has no `sh` unless Git for Windows put one on `PATH`, so the editor is run through `cmd` instead.
+#[cfg(not(unix))]
+fn command(editor: &str, path: &Path) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    let mut command = Command::new("cmd");
+    // `cmd` does its own quoting, which `arg` would escape again. With `/S` it only strips the outer quotes,
+    // so a quoted editor path like `"C:\Program Files\..." -w` survives.
+    command.raw_arg(format!("/S /C \"{} \"{}\"\"", editor, path.display()));
+
+    command
+}
+
+/// `$GIT_EDITOR`, `core.editor`, `$VISUAL` and `$EDITOR` in the order git looks at them.
+fn editor(repository: &git2::Repository) -> String {
+    let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+    env("GIT_EDITOR")
+        .or_else(|| {
+            repository
+                .config()
+                .and_then(|config| config.get_string("core.editor"))
+                .ok()
+        })
+        .or_else(|| env("VISUAL"))
+        .or_else(|| env("EDITOR"))
+        .unwrap_or(DEFAULT_EDITOR.to_string())
+}
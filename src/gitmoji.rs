@@ -0,0 +1,263 @@
+use crate::config::GitmojiFormat;
+
+/// A gitmoji from gitmoji.dev: its emoji, its shortcode and the description the model picks it by.
+pub type Gitmoji = (&'static str, &'static str, &'static str);
+
+/// The official gitmoji list.
+pub const GITMOJIS: &[Gitmoji] = &[
+    ("🎨", ":art:", "Improve structure / format of the code."),
+    ("⚡️", ":zap:", "Improve performance."),
+    ("🔥", ":fire:", "Remove code or files."),
+    ("🐛", ":bug:", "Fix a bug."),
+    ("🚑️", ":ambulance:", "Critical hotfix."),
+    ("✨", ":sparkles:", "Introduce new features."),
+    ("📝", ":memo:", "Add or update documentation."),
+    ("🚀", ":rocket:", "Deploy stuff."),
+    ("💄", ":lipstick:", "Add or update the UI and style files."),
+    ("🎉", ":tada:", "Begin a project."),
+    ("✅", ":white_check_mark:", "Add, update, or pass tests."),
+    ("🔒️", ":lock:", "Fix security or privacy issues."),
+    ("🔐", ":closed_lock_with_key:", "Add or update secrets."),
+    ("🔖", ":bookmark:", "Release / Version tags."),
+    ("🚨", ":rotating_light:", "Fix compiler / linter warnings."),
+    ("🚧", ":construction:", "Work in progress."),
+    ("💚", ":green_heart:", "Fix CI Build."),
+    ("⬇️", ":arrow_down:", "Downgrade dependencies."),
+    ("⬆️", ":arrow_up:", "Upgrade dependencies."),
+    ("📌", ":pushpin:", "Pin dependencies to specific versions."),
+    ("👷", ":construction_worker:", "Add or update CI build system."),
+    (
+        "📈",
+        ":chart_with_upwards_trend:",
+        "Add or update analytics or track code.",
+    ),
+    ("♻️", ":recycle:", "Refactor code."),
+    ("➕", ":heavy_plus_sign:", "Add a dependency."),
+    ("➖", ":heavy_minus_sign:", "Remove a dependency."),
+    ("🔧", ":wrench:", "Add or update configuration files."),
+    ("🔨", ":hammer:", "Add or update development scripts."),
+    ("🌐", ":globe_with_meridians:", "Internationalization and localization."),
+    ("✏️", ":pencil2:", "Fix typos."),
+    ("💩", ":poop:", "Write bad code that needs to be improved."),
+    ("⏪️", ":rewind:", "Revert changes."),
+    ("🔀", ":twisted_rightwards_arrows:", "Merge branches."),
+    ("📦️", ":package:", "Add or update compiled files or packages."),
+    ("👽️", ":alien:", "Update code due to external API changes."),
+    (
+        "🚚",
+        ":truck:",
+        "Move or rename resources (e.g.: files, paths, routes).",
+    ),
+    ("📄", ":page_facing_up:", "Add or update license."),
+    ("💥", ":boom:", "Introduce breaking changes."),
+    ("🍱", ":bento:", "Add or update assets."),
+    ("♿️", ":wheelchair:", "Improve accessibility."),
+    ("💡", ":bulb:", "Add or update comments in source code."),
+    ("🍻", ":beers:", "Write code drunkenly."),
+    ("💬", ":speech_balloon:", "Add or update text and literals."),
+    ("🗃️", ":card_file_box:", "Perform database related changes."),
+    ("🔊", ":loud_sound:", "Add or update logs."),
+    ("🔇", ":mute:", "Remove logs."),
+    ("👥", ":busts_in_silhouette:", "Add or update contributor(s)."),
+    ("🚸", ":children_crossing:", "Improve user experience / usability."),
+    ("🏗️", ":building_construction:", "Make architectural changes."),
+    ("📱", ":iphone:", "Work on responsive design."),
+    ("🤡", ":clown_face:", "Mock things."),
+    ("🥚", ":egg:", "Add or update an easter egg."),
+    ("🙈", ":see_no_evil:", "Add or update a .gitignore file."),
+    ("📸", ":camera_flash:", "Add or update snapshots."),
+    ("⚗️", ":alembic:", "Perform experiments."),
+    ("🔍️", ":mag:", "Improve SEO."),
+    ("🏷️", ":label:", "Add or update types."),
+    ("🌱", ":seedling:", "Add or update seed files."),
+    (
+        "🚩",
+        ":triangular_flag_on_post:",
+        "Add, update, or remove feature flags.",
+    ),
+    ("🥅", ":goal_net:", "Catch errors."),
+    ("💫", ":dizzy:", "Add or update animations and transitions."),
+    ("🗑️", ":wastebasket:", "Deprecate code that needs to be cleaned up."),
+    (
+        "🛂",
+        ":passport_control:",
+        "Work on code related to authorization, roles and permissions.",
+    ),
+    ("🩹", ":adhesive_bandage:", "Simple fix for a non-critical issue."),
+    ("🧐", ":monocle_face:", "Data exploration/inspection."),
+    ("⚰️", ":coffin:", "Remove dead code."),
+    ("🧪", ":test_tube:", "Add a failing test."),
+    ("👔", ":necktie:", "Add or update business logic."),
+    ("🩺", ":stethoscope:", "Add or update healthcheck."),
+    ("🧱", ":bricks:", "Infrastructure related changes."),
+    ("🧑‍💻", ":technologist:", "Improve developer experience."),
+    (
+        "💸",
+        ":money_with_wings:",
+        "Add sponsorships or money related infrastructure.",
+    ),
+    (
+        "🧵",
+        ":thread:",
+        "Add or update code related to multithreading or concurrency.",
+    ),
+    ("🦺", ":safety_vest:", "Add or update code related to validation."),
+    ("✈️", ":airplane:", "Improve offline support."),
+];
+
+/// The gitmoji for each action of the vocabulary plain messages are written with.
+pub const ACTION_GITMOJIS: &[(&str, &str)] = &[
+    ("feat", ":sparkles:"),
+    ("fix", ":bug:"),
+    ("docs", ":memo:"),
+    ("refactor", ":recycle:"),
+    ("enhance", ":zap:"),
+    ("test", ":white_check_mark:"),
+    ("build", ":construction_worker:"),
+    ("deps", ":arrow_up:"),
+];
+
+/// Writes the gitmoji at the start of the first line in `format`. An `action: ` prefix the model may have
+/// used instead is replaced by the action's gitmoji. Anything else is left for `validate` to report.
+pub fn apply(message: &str, format: GitmojiFormat) -> String {
+    let (subject, rest) = message.split_once('\n').unwrap_or((message, ""));
+
+    let subject = match leading_gitmoji(subject) {
+        Some((gitmoji, description)) => format!("{} {}", render(gitmoji, format), description),
+        None => match action_prefix(subject) {
+            Some((gitmoji, description)) => format!("{} {}", render(gitmoji, format), description),
+            None => subject.to_string(),
+        },
+    };
+
+    if rest.is_empty() {
+        subject
+    } else {
+        format!("{}\n{}", subject, rest)
+    }
+}
+
+/// Checks that the first line starts with a gitmoji, as emoji or shortcode, followed by a description.
+pub fn validate(message: &str) -> Result<(), String> {
+    let subject = message.trim().lines().next().unwrap_or("");
+
+    match leading_gitmoji(subject) {
+        Some((_, description)) if !description.is_empty() => Ok(()),
+        Some(_) => Err("the gitmoji must be followed by a description".to_string()),
+        None => Err("the first line must start with a gitmoji".to_string()),
+    }
+}
+
+fn render(gitmoji: &Gitmoji, format: GitmojiFormat) -> &'static str {
+    match format {
+        GitmojiFormat::Emoji => gitmoji.0,
+        GitmojiFormat::Shortcode => gitmoji.1,
+    }
+}
+
+/// The gitmoji `subject` starts with, and the rest of it.
+fn leading_gitmoji(subject: &str) -> Option<(&'static Gitmoji, &str)> {
+    let subject = subject.trim_start();
+
+    GITMOJIS.iter().find_map(|gitmoji| {
+        // the variation selector is often left out, e.g. `⚡` for `⚡️`
+        let emoji = gitmoji.0.trim_end_matches('\u{fe0f}');
+        let rest = subject
+            .strip_prefix(gitmoji.1)
+            .or_else(|| subject.strip_prefix(emoji))?;
+        let rest = rest.strip_prefix('\u{fe0f}').unwrap_or(rest);
+
+        Some((gitmoji, rest.trim()))
+    })
+}
+
+/// The gitmoji of an `action: ` or `action(scope): ` prefix, and the capitalized description after it.
+fn action_prefix(subject: &str) -> Option<(&'static Gitmoji, String)> {
+    let (prefix, description) = subject.split_once(':')?;
+    let action = prefix.split('(').next().unwrap_or("").trim().to_lowercase();
+    let (_, code) = ACTION_GITMOJIS.iter().find(|(name, _)| *name == action)?;
+    let gitmoji = GITMOJIS.iter().find(|gitmoji| gitmoji.1 == *code)?;
+
+    let mut chars = description.trim().chars();
+    let description = chars
+        .next()
+        .map(|first| first.to_uppercase().collect::<String>() + chars.as_str())
+        .unwrap_or_default();
+
+    Some((gitmoji, description))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_renders_leading_gitmoji_in_format() {
+        assert_eq!(apply(":bug: Fix the parser", GitmojiFormat::Emoji), "🐛 Fix the parser");
+        assert_eq!(
+            apply("🐛 Fix the parser", GitmojiFormat::Shortcode),
+            ":bug: Fix the parser"
+        );
+        assert_eq!(apply("🐛 Fix the parser", GitmojiFormat::Emoji), "🐛 Fix the parser");
+    }
+
+    #[test]
+    fn apply_restores_variation_selector() {
+        assert_eq!(apply("⚡ Cache lookups", GitmojiFormat::Emoji), "⚡️ Cache lookups");
+        assert_eq!(
+            apply("⚡ Cache lookups", GitmojiFormat::Shortcode),
+            ":zap: Cache lookups"
+        );
+    }
+
+    #[test]
+    fn apply_replaces_action_prefix() {
+        assert_eq!(apply("feat: add a flag", GitmojiFormat::Emoji), "✨ Add a flag");
+        assert_eq!(
+            apply("Fix(api): handle empty input", GitmojiFormat::Shortcode),
+            ":bug: Handle empty input"
+        );
+    }
+
+    #[test]
+    fn apply_keeps_body() {
+        assert_eq!(
+            apply("docs: explain the flag\n\n- list the values", GitmojiFormat::Emoji),
+            "📝 Explain the flag\n\n- list the values"
+        );
+    }
+
+    #[test]
+    fn apply_leaves_other_subjects_untouched() {
+        assert_eq!(apply("Add a flag", GitmojiFormat::Emoji), "Add a flag");
+        assert_eq!(apply("wip: add a flag", GitmojiFormat::Emoji), "wip: add a flag");
+    }
+
+    #[test]
+    fn leading_gitmoji_finds_emoji_and_shortcode() {
+        let (gitmoji, rest) = leading_gitmoji("  :sparkles: Add a flag").unwrap();
+        assert_eq!(gitmoji.1, ":sparkles:");
+        assert_eq!(rest, "Add a flag");
+
+        let (gitmoji, rest) = leading_gitmoji("♻️ Split the parser").unwrap();
+        assert_eq!(gitmoji.1, ":recycle:");
+        assert_eq!(rest, "Split the parser");
+
+        let (gitmoji, rest) = leading_gitmoji("🧑‍💻 Add a dev script").unwrap();
+        assert_eq!(gitmoji.1, ":technologist:");
+        assert_eq!(rest, "Add a dev script");
+    }
+
+    #[test]
+    fn leading_gitmoji_needs_gitmoji_at_start() {
+        assert!(leading_gitmoji("Add a flag ✨").is_none());
+        assert!(leading_gitmoji(":unknown: Add a flag").is_none());
+    }
+
+    #[test]
+    fn validate_requires_gitmoji_and_description() {
+        assert_eq!(validate("✨ Add a flag\n\nbody"), Ok(()));
+        assert!(validate("✨").is_err());
+        assert!(validate("Add a flag").is_err());
+    }
+}
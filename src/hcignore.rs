@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Files that rarely say anything about a change but take up a lot of the prompt.
+/// `.hcignore` can bring any of them back with a `!` pattern.
+const DEFAULT_PATTERNS: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "Gemfile.lock",
+    "composer.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "go.sum",
+    "flake.lock",
+    "*.min.js",
+    "*.min.css",
+    "*.js.map",
+    "*.css.map",
+];
+
+/// Builds the matcher for paths that are committed but left out of the prompt,
+/// from the built-in defaults followed by `.hcignore` at the root of the working tree.
+pub fn matcher(workdir: &Path) -> anyhow::Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(workdir);
+
+    for pattern in DEFAULT_PATTERNS {
+        builder.add_line(None, pattern)?;
+    }
+
+    let path = workdir.join(".hcignore");
+    if path.exists() {
+        if let Some(err) = builder.add(path) {
+            return Err(err.into());
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Whether `path`, relative to the working tree, is excluded from the prompt.
+pub fn is_ignored(matcher: &Gitignore, path: &str) -> bool {
+    matcher.matched_path_or_any_parents(path, false).is_ignore()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A working tree under the system temp dir with `hcignore` as its `.hcignore`, removed again by the caller.
+    fn workdir(name: &str, hcignore: Option<&str>) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("huge-commit-hcignore-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        if let Some(hcignore) = hcignore {
+            std::fs::write(dir.join(".hcignore"), hcignore).unwrap();
+        }
+
+        dir
+    }
+
+    fn ignored(hcignore: Option<&str>, name: &str, paths: &[&str]) -> Vec<bool> {
+        let dir = workdir(name, hcignore);
+        let matcher = matcher(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        paths.iter().map(|path| is_ignored(&matcher, path)).collect()
+    }
+
+    #[test]
+    fn ignores_lockfiles_and_minified_files_by_default() {
+        assert_eq!(
+            ignored(
+                None,
+                "defaults",
+                &["Cargo.lock", "web/package-lock.json", "dist/app.min.js", "src/main.rs"]
+            ),
+            vec![true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn directory_pattern_ignores_everything_below() {
+        assert_eq!(
+            ignored(
+                Some("build/\n"),
+                "directory",
+                &["build/out.txt", "web/build/app.js", "build.rs"]
+            ),
+            vec![true, true, false]
+        );
+    }
+
+    #[test]
+    fn negation_brings_back_a_globbed_file() {
+        assert_eq!(
+            ignored(
+                Some("*.gen.rs\n!keep.gen.rs\n"),
+                "negation",
+                &["src/api.gen.rs", "src/keep.gen.rs", "src/api.rs"]
+            ),
+            vec![true, false, false]
+        );
+    }
+
+    #[test]
+    fn negation_overrides_a_default() {
+        assert_eq!(
+            ignored(Some("!Cargo.lock\n"), "override", &["Cargo.lock", "yarn.lock"]),
+            vec![false, true]
+        );
+    }
+
+    #[test]
+    fn leading_slash_anchors_to_the_root() {
+        assert_eq!(
+            ignored(
+                Some("/root_only.txt\n"),
+                "anchored",
+                &["root_only.txt", "sub/root_only.txt"]
+            ),
+            vec![true, false]
+        );
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        assert_eq!(
+            ignored(Some("# generated\n\n*.snap\n"), "comments", &["a.snap", "# generated"]),
+            vec![true, false]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::changelog;
+use crate::comment_generator::CommentGenerator;
+use crate::committer::Committer;
+
+use git2::{Commit, Oid, Repository};
+
+/// Describes commits that already exist, for pull requests and release notes. Never touches the index or HEAD.
+pub struct History {
+    repository: Repository,
+    comment_generator: CommentGenerator,
+}
+
+impl History {
+    pub fn new(repository: Repository, comment_generator: CommentGenerator) -> Self {
+        History {
+            repository,
+            comment_generator,
+        }
+    }
+
+    /// Generates a pull request title and description for the commits between the merge base with `base` and HEAD.
+    pub async fn pull_request(&self, base: &str) -> anyhow::Result<String> {
+        let head = Committer::head_commit(&self.repository)?.ok_or(crate::UserError::NoCommitsSinceBase)?;
+        let base = self.repository.revparse_single(base)?.peel_to_commit()?;
+        let merge_base = self
+            .repository
+            .find_commit(self.repository.merge_base(base.id(), head.id())?)?;
+
+        let commits = walk(&self.repository, head.id(), Some(merge_base.id()))?
+            .iter()
+            .map(|commit| commit.summary().unwrap_or("").to_string())
+            .collect::<Vec<_>>();
+        if commits.is_empty() {
+            return Err(crate::UserError::NoCommitsSinceBase.into());
+        }
+
+        let mut diff = self
+            .repository
+            .diff_tree_to_tree(Some(&merge_base.tree()?), Some(&head.tree()?), None)?;
+        Committer::find_similar(&mut diff)?;
+        let (files, omitted_files) = Committer::prompt_files(&self.repository, &diff)?;
+
+        self.comment_generator
+            .gen_pull_request(commits, files, omitted_files)
+            .await
+    }
+
+    /// Generates release notes for the non-merge commits in `range`. A single revision means `<revision>..HEAD`.
+    pub async fn changelog(&self, range: &str, format: changelog::Format) -> anyhow::Result<String> {
+        let (from, to) = resolve_range(&self.repository, range)?;
+        let to = match to {
+            Some(to) => to,
+            None => Committer::head_commit(&self.repository)?.ok_or(crate::UserError::NoCommitsInRange)?,
+        };
+
+        let messages = walk(&self.repository, to.id(), from.map(|from| from.id()))?
+            .iter()
+            .filter(|commit| commit.parent_count() <= 1)
+            .map(|commit| commit.message().unwrap_or("").to_string())
+            .collect::<Vec<_>>();
+        if messages.is_empty() {
+            return Err(crate::UserError::NoCommitsInRange.into());
+        }
+
+        self.comment_generator.gen_changelog(messages, format).await
+    }
+}
+
+/// Resolves `range` to the commits it starts after and ends at. `<from>..<to>` gives both,
+/// a single revision gives only the start, leaving the end to the caller (usually HEAD).
+pub fn resolve_range<'r>(
+    repository: &'r Repository,
+    range: &str,
+) -> anyhow::Result<(Option<Commit<'r>>, Option<Commit<'r>>)> {
+    let spec = repository.revparse(range)?;
+    let to = if spec.mode().contains(git2::RevparseMode::RANGE) {
+        spec.to().map(|to| to.peel_to_commit()).transpose()?
+    } else {
+        None
+    };
+    let from = spec.from().map(|from| from.peel_to_commit()).transpose()?;
+
+    Ok((from, to))
+}
+
+/// Lists the commits reachable from `to` but not from `hide`, oldest first.
+pub fn walk(repository: &Repository, to: Oid, hide: Option<Oid>) -> anyhow::Result<Vec<Commit<'_>>> {
+    let mut revwalk = repository.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+    revwalk.push(to)?;
+    if let Some(hide) = hide {
+        revwalk.hide(hide)?;
+    }
+
+    revwalk.map(|id| Ok(repository.find_commit(id?)?)).collect()
+}
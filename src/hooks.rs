@@ -0,0 +1,240 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use clap::Subcommand;
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[clap(about = "Install huge-commit as the prepare-commit-msg hook of this repository.")]
+    Install,
+
+    #[clap(about = "Remove the prepare-commit-msg hook installed by huge-commit.")]
+    Uninstall,
+
+    #[clap(hide = true, about = "Fill the commit message file. Called by the installed hook.")]
+    PrepareCommitMsg {
+        message_file: PathBuf,
+        source: Option<String>,
+        sha: Option<String>,
+    },
+}
+
+const INSTALLED_MARKER: &str = "# installed by huge-commit";
+const CHAINED_SUFFIX: &str = ".pre-huge-commit";
+
+/// Runs the repository's git hooks the way `git commit` would.
+pub struct Hooks {
+    dir: PathBuf,
+    workdir: PathBuf,
+    index_file: PathBuf,
+    message_file: PathBuf,
+}
+
+impl Hooks {
+    /// Locates the hooks directory from `core.hooksPath`, falling back to `$GIT_COMMON_DIR/hooks`.
+    pub fn new(repository: &git2::Repository) -> anyhow::Result<Self> {
+        let workdir = repository.workdir().unwrap_or(repository.path()).to_path_buf();
+
+        let dir = match repository.config()?.get_path("core.hooksPath") {
+            Ok(path) if path.is_relative() => workdir.join(path),
+            Ok(path) => path,
+            Err(_) => Self::common_dir(repository).join("hooks"),
+        };
+
+        Ok(Hooks {
+            dir,
+            workdir,
+            index_file: repository.path().join("index"),
+            message_file: repository.path().join("COMMIT_EDITMSG"),
+        })
+    }
+
+    /// Runs hook `name` with `args`, failing if it exits with a non-zero status.
+    /// A missing or non-executable hook is skipped, as git does.
+    pub fn run(&self, name: &str, args: &[&str]) -> anyhow::Result<()> {
+        let path = self.dir.join(name);
+        if !Self::is_executable(&path) {
+            return Ok(());
+        }
+
+        let Some(mut command) = Self::command(&path) else {
+            eprintln!("skipping the {} hook, no `sh` was found to run it with.", name);
+            return Ok(());
+        };
+        let status = command
+            .args(args)
+            .current_dir(&self.workdir)
+            .env("GIT_INDEX_FILE", &self.index_file)
+            .env("GIT_EDITOR", ":")
+            .stdin(Stdio::null())
+            // like git, so hook output never mixes with the message or JSON printed on stdout
+            .stdout(std::io::stderr())
+            .status();
+
+        match status {
+            Ok(status) if status.success() => Ok(()),
+            Ok(_) => Err(crate::UserError::HookFailed(name.to_string()).into()),
+            // a hook that never ran has nothing to say about the commit
+            Err(err) => {
+                eprintln!("skipping the {} hook, it could not be started: {}", name, err);
+                Ok(())
+            }
+        }
+    }
+
+    /// Runs hook `name` on a message file holding `message`, followed by `args`,
+    /// and returns the message as the hook left it.
+    pub fn run_with_message(&self, name: &str, message: &str, args: &[&str]) -> anyhow::Result<String> {
+        if !Self::is_executable(&self.dir.join(name)) {
+            return Ok(message.to_string());
+        }
+
+        std::fs::write(&self.message_file, message)?;
+
+        let message_file = self.message_file.to_string_lossy();
+        let args = [&[message_file.as_ref()], args].concat();
+        self.run(name, &args)?;
+
+        Ok(std::fs::read_to_string(&self.message_file)?)
+    }
+
+    /// Writes a prepare-commit-msg hook that fills the message with huge-commit.
+    /// An existing hook is kept next to it and run first.
+    pub fn install(&self) -> anyhow::Result<()> {
+        let path = self.dir.join("prepare-commit-msg");
+        if Self::is_installed(&path) {
+            return Err(crate::UserError::HookAlreadyInstalled.into());
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+        if path.exists() {
+            std::fs::rename(&path, Self::chained_path(&path))?;
+        }
+
+        let executable = std::env::current_exe()?.to_string_lossy().replace('\'', r"'\''");
+        let script = format!(
+            r#"#!/bin/sh
+{INSTALLED_MARKER}
+if [ -x "$0{CHAINED_SUFFIX}" ]; then
+    "$0{CHAINED_SUFFIX}" "$@" || exit $?
+fi
+'{executable}' hook prepare-commit-msg "$@"
+"#
+        );
+        std::fs::write(&path, script)?;
+        Self::make_executable(&path)?;
+
+        println!("installed {}", path.display());
+
+        Ok(())
+    }
+
+    /// Removes the hook written by `install`, restoring the hook it replaced.
+    pub fn uninstall(&self) -> anyhow::Result<()> {
+        let path = self.dir.join("prepare-commit-msg");
+        if !Self::is_installed(&path) {
+            return Err(crate::UserError::HookNotInstalled.into());
+        }
+
+        std::fs::remove_file(&path)?;
+        let chained = Self::chained_path(&path);
+        if chained.exists() {
+            std::fs::rename(&chained, &path)?;
+        }
+
+        println!("uninstalled {}", path.display());
+
+        Ok(())
+    }
+
+    /// Whether the prepare-commit-msg hook should generate a message for this `source`.
+    /// Messages given with `-m`/`-F`, merges, squashes and amends are left untouched.
+    pub fn should_prepare(source: Option<&str>) -> bool {
+        matches!(source, None | Some("template"))
+    }
+
+    fn is_installed(path: &Path) -> bool {
+        std::fs::read_to_string(path)
+            .map(|script| script.contains(INSTALLED_MARKER))
+            .unwrap_or(false)
+    }
+
+    fn chained_path(path: &Path) -> PathBuf {
+        let mut chained = path.as_os_str().to_owned();
+        chained.push(CHAINED_SUFFIX);
+
+        PathBuf::from(chained)
+    }
+
+    #[cfg(unix)]
+    fn make_executable(path: &Path) -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn make_executable(_path: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Linked worktrees keep hooks in the main repository's git dir, which `commondir` points to.
+    fn common_dir(repository: &git2::Repository) -> PathBuf {
+        let git_dir = repository.path();
+
+        std::fs::read_to_string(git_dir.join("commondir"))
+            .map(|dir| git_dir.join(dir.trim()))
+            .unwrap_or(git_dir.to_path_buf())
+    }
+
+    #[cfg(unix)]
+    fn command(path: &Path) -> Option<std::process::Command> {
+        Some(std::process::Command::new(path))
+    }
+
+    /// Hooks are shell scripts Windows can't start by itself, so they run through `sh` like Git for Windows does.
+    #[cfg(not(unix))]
+    fn command(path: &Path) -> Option<std::process::Command> {
+        let mut command = std::process::Command::new(Self::find_sh()?);
+        command.arg(path);
+
+        Some(command)
+    }
+
+    /// `sh` on `PATH`, or the one Git for Windows installs next to `git`.
+    #[cfg(not(unix))]
+    fn find_sh() -> Option<PathBuf> {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let dirs = std::env::split_paths(&path).collect::<Vec<_>>();
+
+        let on_path = dirs.iter().map(|dir| dir.join("sh.exe"));
+        // git.exe is in `Git\cmd` or `Git\bin`, sh.exe in `Git\bin`
+        let next_to_git = dirs
+            .iter()
+            .filter(|dir| dir.join("git.exe").is_file())
+            .filter_map(|dir| dir.parent())
+            .map(|root| root.join("bin").join("sh.exe"));
+        let installed = ["ProgramFiles", "ProgramW6432"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .map(|dir| PathBuf::from(dir).join("Git").join("bin").join("sh.exe"));
+
+        on_path.chain(next_to_git).chain(installed).find(|sh| sh.is_file())
+    }
+
+    #[cfg(unix)]
+    fn is_executable(path: &Path) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+
+        path.metadata()
+            .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+
+    #[cfg(not(unix))]
+    fn is_executable(path: &Path) -> bool {
+        path.is_file()
+    }
+}
@@ -0,0 +1,255 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use git2::{DiffFormat, DiffLineType};
+
+/// A single hunk of a diff, addressed by its file and the lines it starts at.
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub path: PathBuf,
+    pub old_start: u32,
+    pub new_start: u32,
+    pub header: String,
+    pub content: String,
+}
+
+impl std::fmt::Display for Hunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.header.trim_end())
+    }
+}
+
+/// A changed file of a diff and its hunks, in diff order.
+/// Binary files and mode-only changes have no hunks.
+#[derive(Debug, Clone)]
+pub struct FileHunks {
+    pub path: PathBuf,
+    pub status: git2::Delta,
+    pub hunks: Vec<Hunk>,
+}
+
+impl std::fmt::Display for FileHunks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.hunks.len() {
+            0 => write!(f, "{}", self.path.display()),
+            1 => write!(f, "{} (1 hunk)", self.path.display()),
+            n => write!(f, "{} ({} hunks)", self.path.display(), n),
+        }
+    }
+}
+
+pub fn delta_path(delta: &git2::DiffDelta) -> PathBuf {
+    delta
+        .new_file()
+        .path()
+        .or(delta.old_file().path())
+        .map(|path| path.to_path_buf())
+        .unwrap_or_default()
+}
+
+pub fn collect(diff: &git2::Diff) -> anyhow::Result<Vec<FileHunks>> {
+    let mut files: Vec<FileHunks> = Vec::new();
+
+    diff.print(DiffFormat::Patch, |delta, hunk, line| {
+        let path = delta_path(&delta);
+        if files.last().map(|file| file.path != path).unwrap_or(true) {
+            files.push(FileHunks {
+                path: path.clone(),
+                status: delta.status(),
+                hunks: Vec::new(),
+            });
+        }
+        let file = files.last_mut().expect("file was just pushed");
+
+        let mut content = String::new();
+        line.content()
+            .read_to_string(&mut content)
+            .expect("Failed to read line");
+
+        match (hunk, line.origin_value()) {
+            (Some(hunk), DiffLineType::HunkHeader) => file.hunks.push(Hunk {
+                path,
+                old_start: hunk.old_start(),
+                new_start: hunk.new_start(),
+                header: content,
+                content: String::new(),
+            }),
+            (Some(_), DiffLineType::Context | DiffLineType::Addition | DiffLineType::Deletion) => {
+                if let Some(hunk) = file.hunks.last_mut() {
+                    hunk.content.push(line.origin());
+                    hunk.content.push_str(&content);
+                }
+            }
+            (Some(_), _) => {
+                if let Some(hunk) = file.hunks.last_mut() {
+                    hunk.content.push_str(&content);
+                }
+            }
+            (None, _) => {}
+        }
+
+        true
+    })?;
+
+    Ok(files)
+}
+
+/// A subset of the hunks of a diff, used to apply only part of it.
+#[derive(Debug, Default)]
+pub struct HunkSelection {
+    files: HashMap<PathBuf, HashSet<(u32, u32)>>,
+}
+
+impl HunkSelection {
+    pub fn select_file(&mut self, file: &FileHunks) {
+        let hunks = self.files.entry(file.path.clone()).or_default();
+        hunks.extend(file.hunks.iter().map(|hunk| (hunk.old_start, hunk.new_start)));
+    }
+
+    pub fn select_hunk(&mut self, hunk: &Hunk) {
+        self.files
+            .entry(hunk.path.clone())
+            .or_default()
+            .insert((hunk.old_start, hunk.new_start));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Restricts `options` to the selected hunks when they are passed to `apply` or `apply_to_tree`.
+    pub fn configure<'a>(&'a self, options: &mut git2::ApplyOptions<'a>) {
+        let current_path = Rc::new(RefCell::new(None::<PathBuf>));
+
+        let delta_path_cell = current_path.clone();
+        options.delta_callback(move |delta| {
+            let path = delta.map(|delta| delta_path(&delta));
+            let selected = path.as_ref().map(|path| self.files.contains_key(path)).unwrap_or(false);
+            *delta_path_cell.borrow_mut() = path;
+
+            selected
+        });
+
+        options.hunk_callback(move |hunk| {
+            let path = current_path.borrow();
+
+            hunk.zip(path.as_ref())
+                .and_then(|(hunk, path)| {
+                    self.files
+                        .get(path)
+                        .map(|hunks| hunks.contains(&(hunk.old_start(), hunk.new_start())))
+                })
+                .unwrap_or(false)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n17\n18\n19\n20\n";
+    /// `ORIGINAL` with line 2 and line 19 changed, far enough apart to be two hunks.
+    const CHANGED: &str = "1\ntwo\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n17\n18\nnineteen\n20\n";
+
+    /// A repository under the system temp dir with `ORIGINAL` committed as `file.txt` and `CHANGED` in
+    /// the working tree, removed again by the caller.
+    fn repository(name: &str) -> (PathBuf, git2::Repository) {
+        let dir = std::env::temp_dir().join(format!("huge-commit-hunk-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repository = git2::Repository::init(&dir).unwrap();
+
+        std::fs::write(dir.join("file.txt"), ORIGINAL).unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(std::path::Path::new("file.txt")).unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        repository
+            .commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+            .unwrap();
+        drop(tree);
+        std::fs::write(dir.join("file.txt"), CHANGED).unwrap();
+
+        (dir, repository)
+    }
+
+    /// Applies the hunks `select` picks from the working tree changes to HEAD's tree and returns the file.
+    fn apply(repository: &git2::Repository, select: impl Fn(&mut HunkSelection, &FileHunks)) -> String {
+        let head = repository.head().unwrap().peel_to_tree().unwrap();
+        let diff = repository.diff_tree_to_workdir(Some(&head), None).unwrap();
+
+        let files = collect(&diff).unwrap();
+        let mut selection = HunkSelection::default();
+        select(&mut selection, &files[0]);
+
+        let mut options = git2::ApplyOptions::new();
+        selection.configure(&mut options);
+        let mut index = repository.apply_to_tree(&head, &diff, Some(&mut options)).unwrap();
+        let tree = repository.find_tree(index.write_tree_to(repository).unwrap()).unwrap();
+        let blob = tree.get_name("file.txt").unwrap().to_object(repository).unwrap();
+
+        String::from_utf8(blob.as_blob().unwrap().content().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn collect_splits_file_into_hunks() {
+        let (dir, repository) = repository("collect");
+        let head = repository.head().unwrap().peel_to_tree().unwrap();
+        let diff = repository.diff_tree_to_workdir(Some(&head), None).unwrap();
+
+        let files = collect(&diff).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, PathBuf::from("file.txt"));
+        assert_eq!(files[0].status, git2::Delta::Modified);
+        assert_eq!(files[0].hunks.len(), 2);
+        assert!(files[0].hunks[0].content.contains("-2\n+two\n"));
+        assert!(files[0].hunks[1].content.contains("-19\n+nineteen\n"));
+        assert_eq!(files[0].to_string(), "file.txt (2 hunks)");
+    }
+
+    #[test]
+    fn applies_only_selected_hunk() {
+        let (dir, repository) = repository("second-hunk");
+
+        let applied = apply(&repository, |selection, file| selection.select_hunk(&file.hunks[1]));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(applied, ORIGINAL.replace("19\n", "nineteen\n"));
+    }
+
+    #[test]
+    fn applies_every_hunk_of_selected_file() {
+        let (dir, repository) = repository("whole-file");
+
+        let both = apply(&repository, |selection, file| {
+            selection.select_hunk(&file.hunks[0]);
+            selection.select_hunk(&file.hunks[1]);
+        });
+        let whole = apply(&repository, |selection, file| selection.select_file(file));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(both, CHANGED);
+        assert_eq!(whole, CHANGED);
+    }
+
+    #[test]
+    fn applies_nothing_of_unselected_files() {
+        let (dir, repository) = repository("unselected");
+
+        let applied = apply(&repository, |selection, _| {
+            selection.select_file(&FileHunks {
+                path: PathBuf::from("other.txt"),
+                status: git2::Delta::Modified,
+                hunks: Vec::new(),
+            })
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(applied, ORIGINAL);
+    }
+}
@@ -1,11 +1,24 @@
 mod app;
+mod changelog;
+mod classifier;
 mod cli;
 mod comment_generator;
+mod commitlint;
 mod committer;
 mod config;
 mod confirmor;
+mod conventional;
+mod editor;
+mod gitmoji;
+mod hcignore;
+mod history;
+mod hooks;
+mod hunk;
+mod message;
 mod model;
 mod prompt_translator;
+mod signer;
+mod submodule;
 
 use app::App;
 use chrono::TimeZone;
@@ -17,6 +30,52 @@ use std::error::Error;
 enum UserError {
     #[error("No changes to commit.")]
     NoChangesToCommit,
+    #[error("No commit to amend.")]
+    NothingToAmend,
+    #[error("You are in the middle of a {0} -- cannot amend.")]
+    AmendInProgress(String),
+    #[error("You are in the middle of a {0} -- finish or abort it first.")]
+    OperationInProgress(String),
+    #[error("Editor `{0}` could not be started: {1}")]
+    EditorNotStarted(String, String),
+    #[error("Editor `{0}` exited with an error.")]
+    EditorFailed(String),
+    #[error("The commit message is not valid: {0}.")]
+    InvalidCommitMessage(String),
+    #[error("Could not read the commitlint configuration `{0}`: {1}")]
+    InvalidCommitlintConfig(String, String),
+    #[error("Aborting commit due to empty commit message.")]
+    EmptyCommitMessage,
+    #[error("Some files still have conflicts. Resolve them and stage the result first.")]
+    UnresolvedConflicts,
+    #[error("Signing key not set. Use `git config user.signingkey <key>` to set it.")]
+    SigningKeyNotSet,
+    #[error("Unsupported gpg.format `{0}`.")]
+    UnsupportedSigningFormat(String),
+    #[error("Failed to sign the commit: {0}")]
+    SigningFailed(String),
+    #[error("`{0}` hook failed.")]
+    HookFailed(String),
+    #[error("No commits in the given range.")]
+    NoCommitsInRange,
+    #[error("No commits on this branch since it diverged from the base branch.")]
+    NoCommitsSinceBase,
+    #[error("The range must end at HEAD.")]
+    RangeNotAtHead,
+    #[error("The range contains merge commits, which can't be reworded.")]
+    MergeInRange,
+    #[error("The range contains commits that are already on the upstream branch.")]
+    RangeOnUpstream,
+    #[error("huge-commit is already installed as the prepare-commit-msg hook.")]
+    HookAlreadyInstalled,
+    #[error("The prepare-commit-msg hook was not installed by huge-commit.")]
+    HookNotInstalled,
+    #[error("The response was cut off at {0} tokens.")]
+    ResponseCutOff(u32),
+    #[error("The split plan was cut off at {0} tokens. Stage fewer changes and split again.")]
+    SplitPlanCutOff(u32),
+    #[error("The split plan was cut off or is not valid JSON: {0}")]
+    InvalidSplitPlan(String),
     #[error("Model provider not set. Use `huge-commit config set model-provider <provider>` to set it.")]
     ModelProviderNotSet,
 }
@@ -25,12 +84,58 @@ enum UserError {
 async fn main() -> Result<(), Box<dyn Error>> {
     let args = cli::Args::parse();
 
-    let app = App::new()?;
+    let app = App::new(args.directory, args.git_dir, args.work_tree)?;
 
     let base_message = args.base_message.map(|message| message.join(" "));
 
     match args.command {
-        None | Some(cli::Command::Commit) => app.commit(base_message, args.assume_yes).await?,
+        None | Some(cli::Command::Commit) => {
+            let options = committer::CommitOptions {
+                amend: args.amend,
+                gpg_sign: args.gpg_sign,
+                no_verify: args.no_verify,
+                all: args.all,
+                include_untracked: args.include_untracked,
+                dry_run: args.dry_run,
+                candidates: args.candidates,
+            };
+
+            app.commit(base_message, args.assume_yes, options, args.output).await?
+        }
+        Some(cli::Command::Split) => {
+            let options = committer::CommitOptions {
+                gpg_sign: args.gpg_sign,
+                no_verify: args.no_verify,
+                all: args.all,
+                include_untracked: args.include_untracked,
+                ..Default::default()
+            };
+
+            app.split(base_message, args.assume_yes, options).await?
+        }
+        Some(cli::Command::Reword { range }) => {
+            let options = committer::CommitOptions {
+                gpg_sign: args.gpg_sign,
+                no_verify: args.no_verify,
+                ..Default::default()
+            };
+
+            app.reword(&range, args.assume_yes, options).await?
+        }
+        Some(cli::Command::Pr { base, file }) => app.pull_request(&base, file.as_deref()).await?,
+        Some(cli::Command::Changelog { range, format, insert }) => {
+            app.changelog(&range, format, insert.as_deref()).await?
+        }
+        Some(cli::Command::Hook(hooks::Command::Install)) => app.install_hook()?,
+        Some(cli::Command::Hook(hooks::Command::Uninstall)) => app.uninstall_hook()?,
+        Some(cli::Command::Hook(hooks::Command::PrepareCommitMsg {
+            message_file, source, ..
+        })) => {
+            // a failing hook would block `git commit`, so errors are only reported
+            if let Err(err) = app.prepare_commit_msg(&message_file, source.as_deref()).await {
+                eprintln!("huge-commit: {}", err);
+            }
+        }
         Some(cli::Command::Config(config::Command::Get { key })) => {
             if key == config::Item::ModelProvider {
                 if let Some(value) = config::get_model_provider()? {
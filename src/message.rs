@@ -0,0 +1,195 @@
+use crate::config::MessageShape;
+
+/// Column the body is wrapped at, as `git log` and most review tools expect.
+pub const BODY_WIDTH: usize = 72;
+/// Subject length over which a warning is shown, unless `subject-length` is set.
+pub const DEFAULT_SUBJECT_LENGTH: usize = 72;
+
+/// Cleans up a generated message: drops code fences, a `Commit message:` label and quotes around it,
+/// then lays it out in `shape` with a blank line between the subject and the body wrapped at `BODY_WIDTH`.
+pub fn tidy(message: &str, shape: MessageShape) -> String {
+    let message = message
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let message = message.trim();
+    let message = strip_label(message);
+    let message = strip_quotes(message);
+
+    let mut lines = message.lines().skip_while(|line| line.trim().is_empty());
+    let subject = strip_quotes(lines.next().unwrap_or("").trim());
+    let body = lines.collect::<Vec<_>>().join("\n");
+    let body = body.trim_matches('\n').trim_end();
+
+    match shape {
+        MessageShape::Subject => subject.to_string(),
+        MessageShape::SubjectAndBody if body.trim().is_empty() => subject.to_string(),
+        MessageShape::SubjectAndBody => format!("{}\n\n{}", subject, wrap(body, BODY_WIDTH)),
+    }
+}
+
+/// Wraps each line of `text` at `width`, keeping list markers as a hanging indent.
+/// Indented lines are left alone, as they are usually code.
+pub fn wrap(text: &str, width: usize) -> String {
+    text.lines()
+        .map(|line| {
+            if line.chars().count() <= width || line.starts_with(' ') || line.starts_with('\t') {
+                return line.to_string();
+            }
+
+            let indent = " ".repeat(list_marker_width(line));
+            let mut wrapped = Vec::new();
+            let mut current = String::new();
+            for word in line.split_whitespace() {
+                if current.trim().is_empty() {
+                    current.push_str(word);
+                } else if current.chars().count() + 1 + word.chars().count() <= width {
+                    current.push(' ');
+                    current.push_str(word);
+                } else {
+                    wrapped.push(current);
+                    current = format!("{}{}", indent, word);
+                }
+            }
+            wrapped.push(current);
+
+            wrapped.join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Width of a leading `- `, `* ` or `1. ` marker, which continuation lines are indented by.
+fn list_marker_width(line: &str) -> usize {
+    if line.starts_with("- ") || line.starts_with("* ") {
+        return 2;
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        digits + 2
+    } else {
+        0
+    }
+}
+
+fn strip_label(message: &str) -> &str {
+    const LABEL: &str = "commit message:";
+
+    match message.get(..LABEL.len()) {
+        Some(label) if label.eq_ignore_ascii_case(LABEL) => message[LABEL.len()..].trim_start(),
+        _ => message,
+    }
+}
+
+fn strip_quotes(text: &str) -> &str {
+    for quote in ['"', '\'', '`'] {
+        if text.len() >= 2 && text.starts_with(quote) && text.ends_with(quote) {
+            let inner = &text[1..text.len() - 1];
+            // `Fix "foo" and "bar"` is not a quoted message
+            if !inner.contains(quote) {
+                return inner.trim();
+            }
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tidy_strips_fences_label_and_quotes() {
+        assert_eq!(
+            tidy(
+                "```\nCommit message: \"fix: handle empty input\"\n```",
+                MessageShape::Subject
+            ),
+            "fix: handle empty input"
+        );
+        assert_eq!(
+            tidy("`fix: handle empty input`", MessageShape::Subject),
+            "fix: handle empty input"
+        );
+    }
+
+    #[test]
+    fn tidy_keeps_inner_quotes() {
+        assert_eq!(
+            tidy("\"Rename \"foo\" to \"bar\"\"", MessageShape::Subject),
+            "\"Rename \"foo\" to \"bar\"\""
+        );
+    }
+
+    #[test]
+    fn tidy_subject_shape_drops_body() {
+        assert_eq!(
+            tidy("fix: handle empty input\n\nThe parser panicked.", MessageShape::Subject),
+            "fix: handle empty input"
+        );
+    }
+
+    #[test]
+    fn tidy_separates_subject_and_body_with_blank_line() {
+        assert_eq!(
+            tidy(
+                "\n\nfix: handle empty input\nThe parser panicked.\n\n",
+                MessageShape::SubjectAndBody
+            ),
+            "fix: handle empty input\n\nThe parser panicked."
+        );
+        assert_eq!(
+            tidy("fix: handle empty input\n\n\n", MessageShape::SubjectAndBody),
+            "fix: handle empty input"
+        );
+    }
+
+    #[test]
+    fn tidy_wraps_body() {
+        let body = "word ".repeat(20);
+        let message = tidy(&format!("fix: x\n\n{}", body), MessageShape::SubjectAndBody);
+
+        assert!(message.lines().all(|line| line.chars().count() <= BODY_WIDTH));
+        assert_eq!(message.lines().count(), 4);
+    }
+
+    #[test]
+    fn wrap_breaks_long_lines_at_words() {
+        assert_eq!(wrap("aaa bbb ccc ddd", 7), "aaa bbb\nccc ddd");
+        assert_eq!(wrap("short", 7), "short");
+    }
+
+    #[test]
+    fn wrap_keeps_words_longer_than_width() {
+        assert_eq!(
+            wrap("a https://example.com/long/path b", 10),
+            "a\nhttps://example.com/long/path\nb"
+        );
+    }
+
+    #[test]
+    fn wrap_indents_list_items() {
+        assert_eq!(wrap("- aaa bbb ccc", 7), "- aaa\n  bbb\n  ccc");
+        assert_eq!(wrap("* aaa bbb ccc", 7), "* aaa\n  bbb\n  ccc");
+        assert_eq!(wrap("10. aaa bbb", 8), "10. aaa\n    bbb");
+    }
+
+    #[test]
+    fn wrap_leaves_indented_lines_alone() {
+        let code = "    let value = compute(first_argument, second_argument);";
+
+        assert_eq!(wrap(code, 20), code);
+        assert_eq!(
+            wrap("\tlet value = compute(first_argument);", 20),
+            "\tlet value = compute(first_argument);"
+        );
+    }
+
+    #[test]
+    fn wrap_keeps_blank_lines() {
+        assert_eq!(wrap("aaa bbb\n\nccc ddd", 3), "aaa\nbbb\n\nccc\nddd");
+    }
+}
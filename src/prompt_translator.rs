@@ -11,11 +11,29 @@ pub enum PromptTranslator {
     Anthropic(AnthropicTranslator),
 }
 
+/// The streamed response to a prompt. Fails when the response is cut off at `max_tokens`.
+pub type Response = Pin<Box<dyn Stream<Item = anyhow::Result<String>>>>;
+
 impl PromptTranslator {
-    pub async fn translate(&self, prompt: String) -> anyhow::Result<Pin<Box<dyn Stream<Item = String>>>> {
+    /// Sends `prompt`, letting the response take up to `max_tokens` tokens.
+    pub async fn translate(&self, prompt: String, max_tokens: u32) -> anyhow::Result<Response> {
         match self {
-            Self::OpenAI(translator) => Ok(Box::pin(translator.translate(prompt).await?)),
-            Self::Anthropic(translator) => Ok(Box::pin(translator.translate(prompt).await?)),
+            Self::OpenAI(translator) => Ok(Box::pin(translator.translate(prompt, max_tokens).await?)),
+            Self::Anthropic(translator) => Ok(Box::pin(translator.translate(prompt, max_tokens).await?)),
+        }
+    }
+
+    pub fn provider(&self) -> &'static str {
+        match self {
+            Self::OpenAI(_) => "openai",
+            Self::Anthropic(_) => "anthropic",
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        match self {
+            Self::OpenAI(translator) => &translator.model,
+            Self::Anthropic(translator) => &translator.model,
         }
     }
 }
@@ -31,7 +49,13 @@ impl OpenAITranslator {
 }
 
 impl OpenAITranslator {
-    async fn translate(&self, prompt: String) -> anyhow::Result<impl Stream<Item = String>> {
+    /// `max_tokens` is only used to report a cut-off response. Without a cap, OpenAI lets the response take
+    /// the rest of the context window, which a fixed cap could exceed for small models.
+    async fn translate(
+        &self,
+        prompt: String,
+        max_tokens: u32,
+    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<String>>> {
         let api_key = config::get(config::Item::OpenaiApiKey)?.expect("openai-api-key not set");
 
         openai::set_key(api_key);
@@ -49,14 +73,21 @@ impl OpenAITranslator {
         .await?;
 
         let stream = tokio_stream::wrappers::ReceiverStream::new(translated);
-        let stream = stream.map(|data: ChatCompletionGeneric<ChatCompletionChoiceDelta>| {
-            data.choices
+        let stream = stream.map(move |data: ChatCompletionGeneric<ChatCompletionChoiceDelta>| {
+            if data
+                .choices
+                .iter()
+                .any(|c| c.finish_reason.as_deref() == Some("length"))
+            {
+                return Err(crate::UserError::ResponseCutOff(max_tokens).into());
+            }
+
+            Ok(data
+                .choices
                 .iter()
-                .map(|c| c.delta.clone().content)
-                .filter(|c| c.is_some())
-                .map(|f| f.unwrap())
+                .filter_map(|c| c.delta.clone().content)
                 .collect::<Vec<String>>()
-                .join(" ")
+                .join(" "))
         });
 
         Ok(stream)
@@ -74,7 +105,11 @@ impl AnthropicTranslator {
 }
 
 impl AnthropicTranslator {
-    async fn translate(&self, prompt: String) -> anyhow::Result<impl Stream<Item = String>> {
+    async fn translate(
+        &self,
+        prompt: String,
+        max_tokens: u32,
+    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<String>>> {
         let api_key = config::get(config::Item::AnthropicApiKey)?.expect("anthropic-api-key not set");
         let client = reqwest::Client::new();
         let response = client
@@ -86,7 +121,7 @@ impl AnthropicTranslator {
                 "messages": serde_json::json!([{"role": "user", "content": prompt}]),
                 "model": self.model,
                 "stream": true,
-                "max_tokens": 400,
+                "max_tokens": max_tokens,
                 "temperature": 0.7,
             }))
             .send()
@@ -97,27 +132,72 @@ impl AnthropicTranslator {
             .map(|chunk| {
                 chunk
                     .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
-                    .map_err(|e| futures::io::Error::new(futures::io::ErrorKind::Other, e))
+                    .map_err(futures::io::Error::other)
             })
             .filter_map(|result| async move { result.ok() })
             .filter(|data| futures::future::ready(!data.is_empty()))
-            .map(|data| {
-                //println!("{}", &data);
-                let lines: Vec<&str> = data.split('\n').collect();
-                let json_data = lines
-                    .iter()
-                    .find(|line| line.find("{").is_some())
-                    .and_then(|line| serde_json::from_str::<serde_json::Value>(line.trim_start_matches("data: ")).ok());
-
-                json_data
-                    //.and_then(|value| value.get("data").cloned())
-                    //.and_then(|data| serde_json::from_value::<serde_json::Value>(data).ok())
-                    .and_then(|value| value.get("delta").cloned())
-                    .and_then(|delta| delta.get("text").cloned())
-                    .and_then(|text| text.as_str().map(|s| s.to_string()))
-                    .unwrap_or_default()
-            });
+            .flat_map(move |data| futures::stream::iter(Self::events(&data, max_tokens)));
 
         Ok(stream)
     }
+    /// The text of the events in a chunk of the stream. A chunk may hold several events,
+    /// e.g. the last text and the `message_delta` saying why the response stopped.
+    fn events(data: &str, max_tokens: u32) -> Vec<anyhow::Result<String>> {
+        data.lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter_map(|event| {
+                let delta = event.get("delta")?;
+                if delta.get("stop_reason").and_then(|reason| reason.as_str()) == Some("max_tokens") {
+                    return Some(Err(crate::UserError::ResponseCutOff(max_tokens).into()));
+                }
+
+                delta
+                    .get("text")
+                    .and_then(|text| text.as_str())
+                    .map(|text| Ok(text.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_collect_text_of_every_event_in_chunk() {
+        let data = r#"event: content_block_delta
+data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Add "}}
+
+event: content_block_delta
+data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"a flag"}}
+
+event: message_delta
+data: {"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null}}"#;
+
+        let events = AnthropicTranslator::events(data, 400)
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(events, vec!["Add ", "a flag"]);
+    }
+
+    #[test]
+    fn events_fail_when_cut_off_at_max_tokens() {
+        let data = r#"event: content_block_delta
+data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"- a"}}
+
+event: message_delta
+data: {"type":"message_delta","delta":{"stop_reason":"max_tokens","stop_sequence":null}}"#;
+
+        let events = AnthropicTranslator::events(data, 400);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1].as_ref().unwrap_err().to_string(),
+            "The response was cut off at 400 tokens."
+        );
+    }
 }
@@ -0,0 +1,137 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Signs commit buffers the way `git commit -S` does, following `gpg.format`.
+#[derive(Debug)]
+pub enum Signer {
+    /// `openpgp` and `x509` formats, which share gpg's command line.
+    Gpg {
+        program: String,
+        key: String,
+    },
+    Ssh {
+        program: String,
+        key: String,
+    },
+}
+
+impl Signer {
+    /// Reads `commit.gpgsign`, `gpg.format`, `user.signingkey` and the program overrides from git config.
+    /// `gpg_sign` is the `-S` flag: `Some(None)` forces signing with the configured key, `Some(Some(key))` with `key`.
+    /// Returns `None` when commits should not be signed.
+    pub fn from_config(
+        config: &git2::Config,
+        committer: &git2::Signature,
+        gpg_sign: Option<&Option<String>>,
+    ) -> anyhow::Result<Option<Self>> {
+        let enabled = gpg_sign.is_some() || config.get_bool("commit.gpgsign").unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+
+        let key = gpg_sign
+            .cloned()
+            .flatten()
+            .filter(|key| !key.is_empty())
+            .or_else(|| config.get_string("user.signingkey").ok());
+        let format = config.get_string("gpg.format").unwrap_or("openpgp".to_string());
+
+        let signer = match format.as_str() {
+            "openpgp" => Signer::Gpg {
+                program: config
+                    .get_string("gpg.openpgp.program")
+                    .or_else(|_| config.get_string("gpg.program"))
+                    .unwrap_or("gpg".to_string()),
+                key: key.unwrap_or_else(|| Self::committer_ident(committer)),
+            },
+            "x509" => Signer::Gpg {
+                program: config.get_string("gpg.x509.program").unwrap_or("gpgsm".to_string()),
+                key: key.unwrap_or_else(|| Self::committer_ident(committer)),
+            },
+            "ssh" => Signer::Ssh {
+                program: config.get_string("gpg.ssh.program").unwrap_or("ssh-keygen".to_string()),
+                key: key.ok_or(crate::UserError::SigningKeyNotSet)?,
+            },
+            format => return Err(crate::UserError::UnsupportedSigningFormat(format.to_string()).into()),
+        };
+
+        Ok(Some(signer))
+    }
+
+    /// Returns the detached signature of `content`, to be stored in the commit's `gpgsig` header.
+    pub fn sign(&self, content: &str) -> anyhow::Result<String> {
+        match self {
+            Signer::Gpg { program, key } => {
+                Self::run(Command::new(program).args(["--status-fd=2", "-bsau", key]), content)
+            }
+            Signer::Ssh { program, key } => {
+                // a literal public key means the private key lives in ssh-agent
+                let literal = key
+                    .strip_prefix("key::")
+                    .or(key.starts_with("ssh-").then_some(key.as_str()));
+
+                match literal {
+                    Some(public_key) => {
+                        let key_file =
+                            std::env::temp_dir().join(format!("huge-commit-signingkey-{}", std::process::id()));
+                        std::fs::write(&key_file, public_key)?;
+                        let signature = Self::run(
+                            Command::new(program)
+                                .args(["-Y", "sign", "-n", "git", "-U", "-f"])
+                                .arg(&key_file),
+                            content,
+                        );
+                        std::fs::remove_file(&key_file)?;
+
+                        signature
+                    }
+                    None => Self::run(
+                        Command::new(program)
+                            .args(["-Y", "sign", "-n", "git", "-f"])
+                            .arg(Self::expand_home(key)),
+                        content,
+                    ),
+                }
+            }
+        }
+    }
+
+    fn run(command: &mut Command, content: &str) -> anyhow::Result<String> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        child
+            .stdin
+            .take()
+            .expect("Failed to open stdin")
+            .write_all(content.as_bytes())?;
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(
+                crate::UserError::SigningFailed(String::from_utf8_lossy(&output.stderr).trim().to_string()).into(),
+            );
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    fn committer_ident(committer: &git2::Signature) -> String {
+        format!(
+            "{} <{}>",
+            committer.name().unwrap_or(""),
+            committer.email().unwrap_or("")
+        )
+    }
+
+    fn expand_home(path: &str) -> PathBuf {
+        match (path.strip_prefix("~/"), directories::BaseDirs::new()) {
+            (Some(rest), Some(base_dirs)) => base_dirs.home_dir().join(rest),
+            _ => PathBuf::from(path),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Write;
+
+/// How many commit subjects are listed for one submodule update.
+const MAX_SUBJECTS: usize = 30;
+
+/// Describes a gitlink change by the commits it brings in or drops, read from the submodule's own repository.
+/// Falls back to the bare pointer change when the submodule is not initialized or the commits are not fetched.
+pub fn describe(repository: &git2::Repository, path: &str, old_id: git2::Oid, new_id: git2::Oid) -> String {
+    let short = |id: git2::Oid| id.to_string().chars().take(7).collect::<String>();
+
+    if old_id.is_zero() {
+        return format!("# submodule {} added at {}\n", path, short(new_id));
+    }
+    if new_id.is_zero() {
+        return format!("# submodule {} removed, was at {}\n", path, short(old_id));
+    }
+
+    let mut description = format!("# submodule {}: {} -> {}\n", path, short(old_id), short(new_id));
+
+    let submodule = match repository.find_submodule(path).and_then(|submodule| submodule.open()) {
+        Ok(submodule) => submodule,
+        Err(_) => {
+            description.push_str("#   submodule is not initialized, commits are unknown\n");
+            return description;
+        }
+    };
+
+    match (
+        subjects_between(&submodule, old_id, new_id),
+        subjects_between(&submodule, new_id, old_id),
+    ) {
+        (Ok(added), Ok(removed)) => {
+            write_subjects(&mut description, "brings in", &added);
+            write_subjects(&mut description, "drops", &removed);
+        }
+        _ => description.push_str("#   commits are not fetched in the submodule\n"),
+    }
+
+    description
+}
+
+/// Subjects of commits reachable from `to` but not from `from`, newest first.
+fn subjects_between(repository: &git2::Repository, from: git2::Oid, to: git2::Oid) -> anyhow::Result<Vec<String>> {
+    let mut revwalk = repository.revwalk()?;
+    revwalk.push(to)?;
+    revwalk.hide(from)?;
+
+    let mut subjects = Vec::new();
+    for id in revwalk {
+        let commit = repository.find_commit(id?)?;
+        subjects.push(commit.summary().unwrap_or("").to_string());
+    }
+
+    Ok(subjects)
+}
+
+fn write_subjects(description: &mut String, verb: &str, subjects: &[String]) {
+    if subjects.is_empty() {
+        return;
+    }
+
+    writeln!(description, "#   {} {} commits:", verb, subjects.len()).expect("Failed to write description");
+    for subject in subjects.iter().take(MAX_SUBJECTS) {
+        writeln!(description, "#   - {}", subject).expect("Failed to write description");
+    }
+    if subjects.len() > MAX_SUBJECTS {
+        writeln!(description, "#   - and {} more", subjects.len() - MAX_SUBJECTS).expect("Failed to write description");
+    }
+}